@@ -0,0 +1,535 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::Error,
+    execution_tree::{
+        self,
+        parser::{Parser, ParserBuilder, ParserScope},
+        Id,
+    },
+    module::{source_directory, Linker},
+    prelude::{args_prelude, io_error, std_prelude},
+    runtime::{Outcome, Runtime},
+    source::Source,
+    syntax_tree::{self, parser::ParserWrapper},
+    testing::{select_test, test_names},
+    value::Value,
+};
+
+type Prelude = Box<dyn Fn(&mut ParserBuilder)>;
+
+/// Entry point for embedding porte: parses, resolves and runs scripts.
+pub struct Engine {
+    parser: ParserWrapper,
+    preludes: Vec<Prelude>,
+    search_path: Vec<PathBuf>,
+}
+
+impl Engine {
+    /// An engine with the standard prelude registered.
+    pub fn new() -> Self {
+        Self::with_args(Vec::new())
+    }
+
+    /// An engine with the standard prelude registered, scripts receiving `args`.
+    pub fn with_args(args: Vec<String>) -> Self {
+        let mut engine = Self::empty();
+        engine.prelude(std_prelude);
+        engine.prelude(args_prelude(args));
+        engine
+    }
+
+    /// An engine without any prelude, scripts only see what they define.
+    pub fn empty() -> Self {
+        let parser = ParserWrapper::new();
+        let preludes = Vec::new();
+        let search_path = Vec::new();
+        Self {
+            parser,
+            preludes,
+            search_path,
+        }
+    }
+
+    /// Registers globals for every script evaluated afterward.
+    pub fn prelude<F>(&mut self, prelude: F)
+    where
+        F: Fn(&mut ParserBuilder) + 'static,
+    {
+        self.preludes.push(Box::new(prelude));
+    }
+
+    /// Adds a directory where imports are looked for when not found next to the importing file.
+    pub fn search_path<P: Into<PathBuf>>(&mut self, directory: P) {
+        self.search_path.push(directory.into());
+    }
+
+    pub fn parse(&self, input: &str) -> Result<execution_tree::Program, Error> {
+        self.parse_source(Source::new("<input>", input))
+    }
+
+    pub fn parse_source(&self, source: Source) -> Result<execution_tree::Program, Error> {
+        let directory = source_directory(&source);
+        let syntax_tree = self.parser.parse_source(source)?;
+        self.resolve(syntax_tree, &directory)
+    }
+
+    /// Links and resolves a program whose relative imports start from `directory`.
+    fn resolve(
+        &self,
+        syntax_tree: syntax_tree::Program,
+        directory: &Path,
+    ) -> Result<execution_tree::Program, Error> {
+        let syntax_tree =
+            Linker::new(&self.parser, &self.search_path).link(syntax_tree, directory)?;
+        Parser::parse(syntax_tree, |builder| self.register_preludes(builder))
+    }
+
+    fn register_preludes(&self, builder: &mut ParserBuilder) {
+        for prelude in &self.preludes {
+            prelude(builder);
+        }
+    }
+
+    pub fn eval(&self, input: &str) -> Result<Value, Error> {
+        self.eval_source(Source::new("<input>", input))
+    }
+
+    /// Runs a script, giving its final value or, when it calls `exit(code)`, `code`.
+    pub fn eval_source(&self, source: Source) -> Result<Value, Error> {
+        let program = self.parse_source(source)?;
+        let mut runtime = Runtime::new();
+        runtime.execute(&program).map(Outcome::into_value)
+    }
+
+    /// Runs a script file, errors report locations relative to its path.
+    pub fn eval_file<P: AsRef<Path>>(&self, path: P) -> Result<Value, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| io_error(error, &path.display().to_string()))?;
+        self.eval_source(Source::new(path.display(), text))
+    }
+
+    /// Names of the tests a script declares at its top level.
+    pub fn tests(&self, source: Source) -> Result<Vec<String>, Error> {
+        let syntax_tree = self.parser.parse_source(source)?;
+        test_names(&syntax_tree)
+    }
+
+    /// Runs the top-level code of a script then the body of one of its tests, in a runtime of
+    /// its own.
+    pub fn eval_test(&self, source: Source, name: &str) -> Result<Outcome, Error> {
+        let directory = source_directory(&source);
+        let syntax_tree = self.parser.parse_source(source)?;
+        let syntax_tree = select_test(syntax_tree, name)?;
+        let program = self.resolve(syntax_tree, &directory)?;
+        let mut runtime = Runtime::new();
+        runtime.execute(&program)
+    }
+
+    /// A session starting with the globals of the preludes.
+    pub fn session(&self) -> Session<'_> {
+        let parser_scope = ParserScope::new_root();
+        let globals = syntax_tree::Program::new(syntax_tree::Scope::new(Vec::new()));
+        let register = |builder: &mut ParserBuilder| self.register_preludes(builder);
+        let program = Parser::parse_in_scope(globals, register, &parser_scope)
+            .expect("preludes only define literals");
+        let mut runtime = Runtime::new();
+        runtime
+            .execute_persistent(&program)
+            .expect("preludes only define literals");
+        let prelude_ids = parser_scope
+            .local_variables()
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect();
+        Session {
+            engine: self,
+            linker: Linker::new(&self.parser, &self.search_path),
+            parser_scope,
+            program,
+            runtime,
+            prelude_ids,
+        }
+    }
+}
+
+/// Evaluates inputs one after the other, each seeing what the previous ones defined.
+pub struct Session<'a> {
+    engine: &'a Engine,
+    /// Modules imported by previous inputs, which later ones share rather than run again.
+    linker: Linker<'a>,
+    parser_scope: ParserScope,
+    /// Every scope resolved so far, closures from previous inputs still referring to theirs.
+    program: execution_tree::Program,
+    runtime: Runtime,
+    prelude_ids: HashSet<Id>,
+}
+
+impl Session<'_> {
+    pub fn eval(&mut self, input: &str) -> Result<Outcome, Error> {
+        self.eval_source(Source::new("<input>", input))
+    }
+
+    /// Evaluates an input, the names it defines and the modules it imports being kept only when
+    /// it succeeds.
+    pub fn eval_source(&mut self, source: Source) -> Result<Outcome, Error> {
+        let bindings = self.parser_scope.bindings();
+        let linked = self.linker.linked();
+        let result = self.eval_source_inner(source);
+        if result.is_err() {
+            self.parser_scope.restore_bindings(bindings);
+            self.linker.restore_linked(linked);
+        }
+        result
+    }
+
+    fn eval_source_inner(&mut self, source: Source) -> Result<Outcome, Error> {
+        let source = source.with_offset(self.program.sources.end());
+        let directory = source_directory(&source);
+        let syntax_tree = self.engine.parser.parse_source(source)?;
+        let syntax_tree = self.linker.link(syntax_tree, &directory)?;
+        let program = Parser::parse_in_scope(syntax_tree, |_| (), &self.parser_scope)?;
+
+        let execution_tree::Program {
+            scopes, sources, ..
+        } = program;
+        self.program.scopes.extend(scopes);
+        self.program.sources.append(sources);
+        self.runtime.execute_persistent(&self.program)
+    }
+
+    /// The variables defined by the inputs, sorted by name.
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        let mut variables: Vec<_> = self
+            .parser_scope
+            .local_variables()
+            .into_iter()
+            // modules are defined under names scripts cannot write
+            .filter(|(name, id)| !self.prelude_ids.contains(id) && !name.starts_with('<'))
+            .map(|(name, id)| (name, self.runtime.variable(&id).unwrap_or(Value::None)))
+            .collect();
+        variables.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+        variables
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Process status of a script that is not valid porte.
+pub const PARSE_ERROR_STATUS: i32 = 65;
+/// Process status of a script referring to names that are not defined.
+pub const RESOLVE_ERROR_STATUS: i32 = 66;
+/// Process status of a script that raised an error while running.
+pub const RUNTIME_ERROR_STATUS: i32 = 70;
+
+/// Whether scripts may exit with that status, the others being out of range or reserved.
+pub fn is_script_status(code: i64) -> bool {
+    (0..=255).contains(&code)
+        && ![
+            PARSE_ERROR_STATUS,
+            RESOLVE_ERROR_STATUS,
+            RUNTIME_ERROR_STATUS,
+        ]
+        .contains(&(code as i32))
+}
+
+/// The process status for the outcome of a script.
+///
+/// Errors give their reserved status. Otherwise the final value decides, `exit(code)` giving
+/// `code`: `false` gives 1, an int gives itself when scripts may use it and any other value 0.
+pub fn exit_status(result: &Result<Value, Error>) -> i32 {
+    match result {
+        Ok(Value::Bool(false)) => 1,
+        Ok(Value::Int(code)) if is_script_status(*code) => *code as i32,
+        Ok(_) => 0,
+        Err(error) => match error.inner() {
+            Error::Exit(code) => *code,
+            Error::Parse { .. } => PARSE_ERROR_STATUS,
+            Error::Resolve(_) => RESOLVE_ERROR_STATUS,
+            _ => RUNTIME_ERROR_STATUS,
+        },
+    }
+}
+
+/// Runs a script on a fresh engine, for tests of the language features.
+#[cfg(test)]
+pub(crate) fn eval(text: &str) -> Value {
+    Engine::new().eval(text).unwrap()
+}
+
+#[test]
+fn test_engine_eval() {
+    let engine = Engine::new();
+    let value = engine.eval("a: 3; add(a, 2)").unwrap();
+    assert_eq!(value.as_number().unwrap(), 5.);
+}
+
+#[test]
+fn test_engine_args() {
+    let engine = Engine::with_args(vec!["a".into(), "b c".into()]);
+    let args = engine.eval("args").unwrap();
+    let args: Vec<_> = args
+        .as_list()
+        .unwrap()
+        .iter()
+        .map(Value::as_string)
+        .collect();
+    assert_eq!(args, [Some("a"), Some("b c")]);
+    assert_eq!(Engine::new().eval("len(args)").unwrap().as_int(), Some(0));
+}
+
+#[test]
+fn test_engine_exit() {
+    let engine = Engine::new();
+    let status = |text| exit_status(&engine.eval(text));
+    assert_eq!(status(r#"out("done")"#), 0);
+    assert_eq!(status("a: 1; true"), 0);
+    assert_eq!(status("false"), 1);
+    assert_eq!(status("3"), 3);
+    assert_eq!(status("300"), 0);
+    assert_eq!(status("65"), 0);
+    assert_eq!(status("exit(3)"), 3);
+    assert_eq!(status("exit(1); 0"), 1);
+    assert_eq!(status("a: ("), PARSE_ERROR_STATUS);
+    assert_eq!(status("a <- 1"), RESOLVE_ERROR_STATUS);
+    assert_eq!(status("add(1, true)"), RUNTIME_ERROR_STATUS);
+    assert_eq!(status("exit(0); false"), 0);
+    assert_eq!(status("exit(65)"), RUNTIME_ERROR_STATUS);
+    // a clean exit is not an error
+    assert_eq!(engine.eval("exit(0)").unwrap().as_int(), Some(0));
+
+    // exits unwind nested scopes, loops, closures and higher-order functions
+    let text = r#"
+        check: (n) => { if n == 3 { exit(4) }; n };
+        loop { { map([1, 2, 3], check) } };
+        out("unreachable")
+    "#;
+    assert_eq!(status(text), 4);
+    let mut session = engine.session();
+    assert!(matches!(session.eval(text), Ok(Outcome::Exit(4))));
+}
+
+#[test]
+fn test_engine_imports() {
+    let root = std::env::temp_dir().join(format!("porte-imports-{}", std::process::id()));
+    let write = |path: &str, text: &str| {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    };
+    write(
+        "lib/counter.pr",
+        "state: { count: 0 }; next: () => { state.count <- state.count + 1 }",
+    );
+    write(
+        "lib/uses.pr",
+        r#"counter: import "counter.pr"; counter.next()"#,
+    );
+    write("shared/greet.pr", r#"greet: (name) => { "hello ${name}" }"#);
+    write("lib/broken.pr", "fail: () => {\n  add(1, true)\n}");
+    write("lib/leaky.pr", "seen: hidden");
+    write(
+        "lib/recursive.pr",
+        "o: obj(); f: none; f <- (n) => { if n == 0 o else f(n - 1) }",
+    );
+    write("lib/tested.pr", r#"x: 1; test "x" { assert_eq(x, 1) }"#);
+    write("lib/a.pr", r#"import "b.pr""#);
+    write("lib/b.pr", r#"import "a.pr""#);
+
+    let mut engine = Engine::new();
+    engine.search_path(root.join("shared"));
+    let eval = |text: &str| engine.eval_source(Source::new(root.join("main.pr").display(), text));
+    // every module runs once, importers sharing its definitions
+    let text = r#"
+        uses: import "lib/uses.pr";
+        counter: import "lib/counter.pr";
+        str([counter.next(), uses.counter.state.count, (import "greet.pr").greet("you")])
+    "#;
+    assert_eq!(eval(text).unwrap().as_string(), Some("[2, 2, hello you]"));
+
+    let error = eval(r#"broken: import "lib/broken.pr"; broken.fail()"#).unwrap_err();
+    let location = error.location().unwrap().to_string();
+    assert!(location.ends_with("broken.pr:2:3"), "{location}");
+    let error = eval(r#"import "lib/a.pr""#).unwrap_err();
+    assert!(
+        matches!(error.inner(), Error::Resolve(message) if message.starts_with("import cycle"))
+    );
+    let error = eval(r#"import "lib/missing.pr""#).unwrap_err();
+    assert!(matches!(error.inner(), Error::Resolve(_)));
+    let tested = eval(r#"(import "lib/tested.pr").x"#).unwrap();
+    assert_eq!(tested.as_int(), Some(1));
+    // recursive functions of modules do not keep the module alive after the run
+    let Value::Object(object) = eval(r#"(import "lib/recursive.pr").f(2)"#).unwrap() else {
+        panic!("expected an object");
+    };
+    assert_eq!(std::rc::Rc::strong_count(&object), 1);
+
+    let mut session = engine.session();
+    let mut eval =
+        |text: &str| session.eval_source(Source::new(root.join("main.pr").display(), text));
+    // modules only see the prelude and other modules, not the names of their importers
+    eval("hidden: 1").unwrap();
+    let error = eval(r#"import "lib/leaky.pr""#).unwrap_err();
+    assert!(matches!(error.inner(), Error::Resolve(_)));
+    // later inputs share the modules of the previous ones instead of running them again
+    eval(r#"counter: import "lib/counter.pr"; counter.next()"#).unwrap();
+    let count = eval(r#"(import "lib/counter.pr").next()"#)
+        .unwrap()
+        .into_value();
+    assert_eq!(count.as_int(), Some(2));
+    // unless the input importing them failed
+    eval(r#"greeter: import "greet.pr"; undefined"#).unwrap_err();
+    let greeting = eval(r#"(import "greet.pr").greet("again")"#)
+        .unwrap()
+        .into_value();
+    assert_eq!(greeting.as_string(), Some("hello again"));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_engine_session() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+    session.eval("count: 0").unwrap();
+    session.eval("next: () => { count <- count + 1 }").unwrap();
+    // an unresolved input runs nothing, a failing one keeps what ran before the error
+    let error = session.eval("next(); undefined").unwrap_err();
+    assert!(matches!(error.inner(), Error::Resolve(_)));
+    let error = session.eval("next(); add(1, true)").unwrap_err();
+    assert!(matches!(error.inner(), Error::Type(_)));
+    assert_eq!(
+        session.eval("next()").unwrap().into_value().as_int(),
+        Some(2)
+    );
+    let out = session.eval("out: 3; out").unwrap().into_value();
+    assert_eq!(out.as_int(), Some(3));
+
+    let names: Vec<_> = session
+        .variables()
+        .into_iter()
+        .map(|(name, value)| format!("{name}: {}", value.repr()))
+        .collect();
+    assert_eq!(names, ["count: 2", "next: <function/0>", "out: 3"]);
+}
+
+#[test]
+fn test_engine_session_failed_definitions() {
+    let engine = Engine::new();
+    let mut session = engine.session();
+    session.eval("count: 5").unwrap();
+    session.eval("get_count: () => { count }").unwrap();
+    // failed inputs do not shadow definitions, whether they fail to resolve or to run
+    session.eval("count: 6; undefined").unwrap_err();
+    assert_eq!(
+        session.eval("count").unwrap().into_value().as_int(),
+        Some(5)
+    );
+    session.eval("count: 7; add(1, true)").unwrap_err();
+    assert_eq!(
+        session.eval("count").unwrap().into_value().as_int(),
+        Some(5)
+    );
+    assert_eq!(
+        session.eval("get_count()").unwrap().into_value().as_int(),
+        Some(5)
+    );
+    session.eval("count: 8").unwrap();
+    assert_eq!(
+        session.eval("count").unwrap().into_value().as_int(),
+        Some(8)
+    );
+    assert_eq!(
+        session.eval("get_count()").unwrap().into_value().as_int(),
+        Some(5)
+    );
+}
+
+#[test]
+fn test_engine_errors() {
+    let engine = Engine::new();
+    let error = engine.eval("a <- 3").unwrap_err();
+    assert!(matches!(error.inner(), Error::Resolve(_)));
+    let error = engine.eval("if 3 true").unwrap_err();
+    assert!(matches!(error.inner(), Error::Type(_)));
+    let error = engine.eval("add(1)").unwrap_err();
+    assert!(matches!(
+        error.inner(),
+        Error::Arity {
+            expected: 2,
+            found: 1
+        }
+    ));
+    let error = engine.eval_file("missing/script.pr").unwrap_err();
+    assert!(matches!(error.inner(), Error::Io(_)));
+    assert!(error.to_string().contains("missing/script.pr"));
+}
+
+#[test]
+fn test_engine_nested_tests() {
+    let engine = Engine::new();
+    assert!(matches!(
+        engine.eval("test \"top\" { 1 }; 2"),
+        Ok(Value::Int(2))
+    ));
+    for text in ["f: () => { test \"t\" { 1 } }", "{ test \"t\" { 1 } }"] {
+        let error = engine.eval(text).unwrap_err();
+        let Error::Resolve(message) = error.inner() else {
+            panic!("expected a resolve error for '{text}'");
+        };
+        assert_eq!(message, "test 't' declared outside the top level");
+    }
+    let source = Source::new("script.pr", "test \"outer\" { test \"inner\" { 1 } }");
+    let error = engine.eval_test(source, "outer").unwrap_err();
+    assert!(matches!(error.inner(), Error::Resolve(_)));
+}
+
+#[test]
+fn test_engine_error_location() {
+    let engine = Engine::new();
+    let source = Source::new("script.pr", "a: 1;\nb: {\n  add(a, true)\n}");
+    let error = engine.eval_source(source).unwrap_err();
+    assert_eq!(error.location().unwrap().to_string(), "script.pr:3:3");
+    let source = Source::new("script.pr", "a: 1;\n\nb <- a");
+    let error = engine.eval_source(source).unwrap_err();
+    assert_eq!(error.location().unwrap().to_string(), "script.pr:3:1");
+}
+
+#[test]
+fn test_engine_error_trace() {
+    let engine = Engine::new();
+    let text = "f: (a) => {\n  add(a, true)\n};\ng: () => { f(1) };\ng()";
+    let error = engine
+        .eval_source(Source::new("script.pr", text))
+        .unwrap_err();
+    let trace: Vec<_> = error
+        .trace()
+        .unwrap()
+        .iter()
+        .map(|entry| entry.to_string())
+        .collect();
+    assert_eq!(
+        trace,
+        vec![
+            "at add (script.pr:2:3)",
+            "at f (script.pr:4:12)",
+            "at g (script.pr:5:1)"
+        ]
+    );
+}
+
+#[test]
+fn test_engine_prelude() {
+    let mut engine = Engine::empty();
+    engine.prelude(|builder| builder.prelude("answer".into(), 42.0.into()));
+    let value = engine.eval("answer").unwrap();
+    assert_eq!(value.as_number().unwrap(), 42.);
+}
@@ -0,0 +1,262 @@
+use std::{collections::BTreeSet, fmt, rc::Rc};
+
+use chumsky::{error::SimpleReason, prelude::Simple};
+
+use crate::{
+    source::{Location, Source},
+    value::Value,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The source text is not valid porte.
+    Parse {
+        source: Rc<Source>,
+        errors: Vec<Simple<char>>,
+    },
+    /// A name, `return` or `break` does not refer to anything in scope.
+    Resolve(String),
+    /// A value was used where another type was required.
+    Type(String),
+    /// A function was called with the wrong number of arguments.
+    Arity { expected: usize, found: usize },
+    /// A native function refused its input.
+    NativeCall(String),
+    /// The operating system reported a failure.
+    Io(std::io::Error),
+    /// An assertion did not hold, `assert_eq` reporting the values it compared.
+    Assertion {
+        message: String,
+        values: Option<Box<(Value, Value)>>,
+    },
+    /// Not a failure: returned by the `exit` native function, the runtime turning it into an
+    /// `Outcome::Exit` at the call site.
+    Exit(i32),
+    /// Another error, raised by the expression at that location.
+    Located {
+        location: Location,
+        error: Box<Error>,
+    },
+    /// Another error, raised while these functions were being called, innermost first.
+    Traced {
+        trace: Vec<TraceEntry>,
+        error: Box<Error>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceEntry {
+    pub function_name: String,
+    pub call_site: Location,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            function_name,
+            call_site,
+        } = self;
+        write!(f, "at {function_name} ({call_site})")
+    }
+}
+
+impl Error {
+    pub fn new_resolve<S: ToString>(message: S) -> Self {
+        Self::Resolve(message.to_string())
+    }
+
+    pub fn new_type<S: ToString>(message: S) -> Self {
+        Self::Type(message.to_string())
+    }
+
+    pub fn new_arity(expected: usize, found: usize) -> Self {
+        Self::Arity { expected, found }
+    }
+
+    pub fn new_native_call<S: ToString>(message: S) -> Self {
+        Self::NativeCall(message.to_string())
+    }
+
+    pub fn new_assertion<S: ToString>(message: S, values: Option<(Value, Value)>) -> Self {
+        let message = message.to_string();
+        let values = values.map(Box::new);
+        Self::Assertion { message, values }
+    }
+
+    /// Attaches a location, unless a more precise one is already known.
+    pub fn at(self, location: Location) -> Self {
+        if matches!(self, Self::Parse { .. }) || self.location().is_some() {
+            return self;
+        }
+        Self::Located {
+            location,
+            error: Box::new(self),
+        }
+    }
+
+    /// Attaches a stack trace, unless one was captured deeper in the stack.
+    pub fn traced<F>(self, trace: F) -> Self
+    where
+        F: FnOnce() -> Vec<TraceEntry>,
+    {
+        if self.trace().is_some() {
+            return self;
+        }
+        Self::Traced {
+            trace: trace(),
+            error: Box::new(self),
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Self::Located { location, .. } => Some(location),
+            Self::Traced { error, .. } => error.location(),
+            _ => None,
+        }
+    }
+
+    pub fn trace(&self) -> Option<&[TraceEntry]> {
+        match self {
+            Self::Traced { trace, .. } => Some(trace),
+            Self::Located { error, .. } => error.trace(),
+            _ => None,
+        }
+    }
+
+    /// The error stripped from its context.
+    pub fn inner(&self) -> &Self {
+        match self {
+            Self::Located { error, .. } | Self::Traced { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse { source, errors } => {
+                for (index, error) in errors.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    write_parse_error(f, source, error)?;
+                }
+                Ok(())
+            }
+            Self::Resolve(message) => write!(f, "resolve error: {message}"),
+            Self::Type(message) => write!(f, "type error: {message}"),
+            Self::Arity { expected, found } => write!(
+                f,
+                "arity error: expected {expected} argument(s), found {found}"
+            ),
+            Self::NativeCall(message) => write!(f, "native call error: {message}"),
+            Self::Io(error) => write!(f, "io error: {error}"),
+            Self::Assertion { message, values } => {
+                write!(f, "assertion error: {message}")?;
+                if let Some((left, right)) = values.as_deref() {
+                    write!(f, "\n  left:  {}\n  right: {}", left.repr(), right.repr())?;
+                }
+                Ok(())
+            }
+            Self::Exit(code) => write!(f, "exited with status {code}"),
+            Self::Located { location, error } => write!(f, "{location}: {error}"),
+            Self::Traced { trace, error } => {
+                write!(f, "{error}")?;
+                for entry in trace {
+                    write!(f, "\n    {entry}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Renders the message, then the offending line with a caret under the span.
+fn write_parse_error(
+    f: &mut fmt::Formatter<'_>,
+    source: &Source,
+    error: &Simple<char>,
+) -> fmt::Result {
+    let found = describe_token(error.found());
+    let message = match error.reason() {
+        SimpleReason::Custom(message) => message.clone(),
+        SimpleReason::Unclosed { delimiter, .. } => {
+            format!("unclosed delimiter '{delimiter}', found {found}")
+        }
+        SimpleReason::Unexpected => match describe_expected(error) {
+            Some(expected) => format!("found {found} but expected {expected}"),
+            None => format!("unexpected {found}"),
+        },
+    };
+    writeln!(f, "parse error: {message}")?;
+
+    let span = error.span();
+    let location = source.locate(&span);
+    let line = source.line(location.line);
+    let gutter = " ".repeat(location.line.to_string().len());
+    let indent: String = line
+        .chars()
+        .take(location.column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = span.len().clamp(
+        1,
+        line.chars().count().max(location.column) - location.column + 1,
+    );
+    writeln!(f, "{gutter}--> {location}")?;
+    writeln!(f, "{gutter} |")?;
+    writeln!(f, "{} | {line}", location.line)?;
+    write!(f, "{gutter} | {indent}{}", "^".repeat(width))
+}
+
+#[test]
+fn test_parse_error_display() {
+    use crate::syntax_tree::parser::ParserWrapper;
+
+    let source = Source::new("script.pr", "a: 1;\n\tb: add(1 x)");
+    let error = ParserWrapper::new().parse_source(source).unwrap_err();
+    let expected = r#"parse error: found 'x' but expected ')', ',', '.' or an operator
+ --> script.pr:2:11
+  |
+2 | 	b: add(1 x)
+  | 	         ^"#;
+    assert_eq!(error.to_string(), expected);
+}
+
+fn describe_token(token: Option<&char>) -> String {
+    match token {
+        Some(c) if c.is_whitespace() => format!("{c:?}"),
+        Some(c) => format!("'{c}'"),
+        None => "end of input".into(),
+    }
+}
+
+/// Summarizes the expected characters, collapsing letters, digits and operator symbols.
+fn describe_expected(error: &Simple<char>) -> Option<String> {
+    let expected: BTreeSet<_> = error
+        .expected()
+        .map(|token| match token {
+            Some(c) if c.is_alphabetic() => "a name".into(),
+            Some(c) if c.is_ascii_digit() => "a number".into(),
+            Some(c) if "!%&*+-/<=>|".contains(*c) => "an operator".into(),
+            token => describe_token(token.as_ref()),
+        })
+        .collect();
+    let mut expected: Vec<String> = expected.into_iter().collect();
+    let last = expected.pop()?;
+    if expected.is_empty() {
+        Some(last)
+    } else {
+        Some(format!("{} or {last}", expected.join(", ")))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
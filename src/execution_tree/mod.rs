@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 
-use crate::value::Value;
+use crate::{
+    source::{Sources, Span},
+    value::Value,
+};
 
 #[derive(Debug)]
 pub struct Program {
     pub main_scope_id: Id,
     pub scopes: HashMap<Id, Scope>,
+    pub sources: Sources,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -21,55 +25,89 @@ impl Id {
 }
 
 #[derive(Debug)]
-pub struct Expr(Box<ExprInner>);
+pub struct Expr {
+    inner: Box<ExprInner>,
+    span: Span,
+}
 
 impl Expr {
+    fn new(inner: ExprInner) -> Self {
+        let inner = Box::new(inner);
+        let span = Span::default();
+        Self { inner, span }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        let Self { inner, .. } = self;
+        Self { inner, span }
+    }
+
     pub fn inner(&self) -> &ExprInner {
-        &self.0
+        &self.inner
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
     }
 
     pub fn new_scope(scope_id: Id) -> Self {
-        Self(Box::new(ExprInner::Scope(scope_id)))
+        Self::new(ExprInner::Scope(scope_id))
     }
 
     pub fn new_literal(literal: Literal) -> Self {
-        Self(Box::new(ExprInner::Literal(literal)))
+        Self::new(ExprInner::Literal(literal))
+    }
+
+    pub fn new_list(list: List) -> Self {
+        Self::new(ExprInner::List(list))
+    }
+
+    pub fn new_object(object: Object) -> Self {
+        Self::new(ExprInner::Object(object))
+    }
+
+    pub fn new_field_call(field_call: FieldCall) -> Self {
+        Self::new(ExprInner::FieldCall(field_call))
+    }
+
+    pub fn new_field_assignment(field_assignment: FieldAssign) -> Self {
+        Self::new(ExprInner::FieldAssign(field_assignment))
     }
 
     pub fn new_variable_definition(variable_definition: VarDef) -> Self {
-        Self(Box::new(ExprInner::VarDef(variable_definition)))
+        Self::new(ExprInner::VarDef(variable_definition))
     }
 
     pub fn new_variable_assignment(variable_assignment: VarAssign) -> Self {
-        Self(Box::new(ExprInner::VarAssign(variable_assignment)))
+        Self::new(ExprInner::VarAssign(variable_assignment))
     }
 
     pub fn new_variable_call(variable_call: VarCall) -> Self {
-        Self(Box::new(ExprInner::VarCall(variable_call)))
+        Self::new(ExprInner::VarCall(variable_call))
     }
 
     pub fn new_function_definition(function_definition: FnDef) -> Self {
-        Self(Box::new(ExprInner::FnDef(function_definition)))
+        Self::new(ExprInner::FnDef(function_definition))
     }
 
     pub fn new_function_call(function_call: FnCall) -> Self {
-        Self(Box::new(ExprInner::FnCall(function_call)))
+        Self::new(ExprInner::FnCall(function_call))
     }
 
     pub fn new_function_return(function_return: FnRet) -> Self {
-        Self(Box::new(ExprInner::FnRet(function_return)))
+        Self::new(ExprInner::FnRet(function_return))
     }
 
     pub fn new_loop(loop_: Loop) -> Self {
-        Self(Box::new(ExprInner::Loop(loop_)))
+        Self::new(ExprInner::Loop(loop_))
     }
 
     pub fn new_loop_break(loop_break: LoopBr) -> Self {
-        Self(Box::new(ExprInner::LoopBr(loop_break)))
+        Self::new(ExprInner::LoopBr(loop_break))
     }
 
     pub fn new_condition(condition: Cond) -> Self {
-        Self(Box::new(ExprInner::Cond(condition)))
+        Self::new(ExprInner::Cond(condition))
     }
 }
 
@@ -77,6 +115,10 @@ impl Expr {
 pub enum ExprInner {
     Scope(Id),
     Literal(Literal),
+    List(List),
+    Object(Object),
+    FieldCall(FieldCall),
+    FieldAssign(FieldAssign),
     VarDef(VarDef),
     VarAssign(VarAssign),
     VarCall(VarCall),
@@ -99,6 +141,29 @@ pub struct Scope {
 #[derive(Debug)]
 pub struct Literal(pub Value);
 
+#[derive(Debug)]
+pub struct List {
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Debug)]
+pub struct Object {
+    pub fields: Vec<(String, Expr)>,
+}
+
+#[derive(Debug)]
+pub struct FieldCall {
+    pub object: Expr,
+    pub field: String,
+}
+
+#[derive(Debug)]
+pub struct FieldAssign {
+    pub variable_id: Id,
+    pub path: Vec<String>,
+    pub value: Expr,
+}
+
 #[derive(Debug)]
 pub struct VarDef {
     pub variable_id: Id,
@@ -120,11 +185,19 @@ pub struct VarCall {
 pub struct FnDef {
     pub parameter_ids: Vec<Id>,
     pub body_scope_id: Id,
+    /// The variables of enclosing scopes the body uses, captured when the function is defined.
+    pub captured_ids: Vec<Id>,
+    /// The variable the function is assigned to when its body uses it. Rather than captured, it
+    /// is bound to the function itself on every call, so that recursive functions do not keep
+    /// themselves alive. Functions calling each other through variables still do.
+    pub self_id: Option<Id>,
 }
 
 #[derive(Debug)]
 pub struct FnCall {
-    pub variable_id: Id,
+    /// Name reported in stack traces.
+    pub function_name: String,
+    pub callee: Expr,
     pub arguments: Vec<Expr>,
 }
 
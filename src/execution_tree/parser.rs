@@ -1,7 +1,9 @@
 use std::{collections::HashMap, rc::Rc, sync::Mutex};
 
 use crate::{
+    error::Error,
     execution_tree::{self, Id},
+    source::Sources,
     syntax_tree,
     value::Value,
 };
@@ -24,7 +26,7 @@ impl ParserBuilder {
         let prelude = self.prelude.clone();
         for (name, value) in prelude.into_iter().rev() {
             let value = syntax_tree::Expr::new_literal(value);
-            let expression = syntax_tree::Expr::new_variable_definition(name, value);
+            let expression = syntax_tree::Expr::new_global(name, value);
             body.instructions.insert(0, expression);
         }
     }
@@ -32,49 +34,74 @@ impl ParserBuilder {
 
 pub struct Parser {
     scopes: HashMap<Id, execution_tree::Scope>,
+    sources: Sources,
 }
 
 impl Parser {
-    fn new() -> Self {
+    fn new(sources: Sources) -> Self {
         let scopes = HashMap::new();
-        Self { scopes }
+        Self { scopes, sources }
+    }
+
+    pub fn parse<F>(
+        syntax_tree: syntax_tree::Program,
+        builder: F,
+    ) -> Result<execution_tree::Program, Error>
+    where
+        F: FnOnce(&mut ParserBuilder),
+    {
+        Self::parse_in_scope(syntax_tree, builder, &ParserScope::new_root())
     }
 
-    pub fn parse<F>(syntax_tree: syntax_tree::Program, builder: F) -> execution_tree::Program
+    /// Resolves a program in a top-level scope which keeps its definitions, for programs resolved
+    /// later in that scope to refer to them.
+    pub fn parse_in_scope<F>(
+        syntax_tree: syntax_tree::Program,
+        builder: F,
+        parser_scope: &ParserScope,
+    ) -> Result<execution_tree::Program, Error>
     where
         F: FnOnce(&mut ParserBuilder),
     {
         let operation = builder;
-        let syntax_tree::Program { mut body } = syntax_tree;
+        let syntax_tree::Program { mut body, sources } = syntax_tree;
 
         let mut builder = ParserBuilder::new();
         operation(&mut builder);
         builder.append_globals(&mut body);
 
-        let mut parser = Self::new();
-        let parser_scope = ParserScope::new_root();
-        let main_scope_id = parser.parse_syntax_tree_scope(body, &parser_scope);
-        let Self { scopes } = parser;
+        let mut parser = Self::new(sources);
+        let main_scope_id = parser.parse_syntax_tree_scope(body, parser_scope)?;
+        let Self { scopes, sources } = parser;
 
-        execution_tree::Program {
+        Ok(execution_tree::Program {
             main_scope_id,
             scopes,
-        }
+            sources,
+        })
     }
 
     pub fn parse_syntax_tree_scope(
         &mut self,
         syntax_tree_scope: syntax_tree::Scope,
         parser_scope: &ParserScope,
-    ) -> execution_tree::Id {
+    ) -> Result<execution_tree::Id, Error> {
         let syntax_tree::Scope { instructions } = syntax_tree_scope;
 
         let scope_id = parser_scope.get_current_id();
         let parent_scope_id = parser_scope.get_parent_id();
         let expressions = instructions
             .into_iter()
-            .map(|expression| self.parse_expression(expression, parser_scope))
-            .collect();
+            .map(|expression| match expression.inner() {
+                // the engine replaces the selected test by its body, others are declarations only
+                syntax_tree::ExprInner::Test(_) if parser_scope.is_top_level() => {
+                    let (_, span) = expression.into_parts();
+                    let none = execution_tree::Literal(Value::None);
+                    Ok(execution_tree::Expr::new_literal(none).with_span(span))
+                }
+                _ => self.parse_expression(expression, parser_scope),
+            })
+            .collect::<Result<_, _>>()?;
         let local_variables = parser_scope.local_variable_ids();
 
         let scope = execution_tree::Scope {
@@ -84,65 +111,116 @@ impl Parser {
             local_variables,
         };
         self.scopes.insert(scope_id, scope);
-        scope_id
+        Ok(scope_id)
     }
 
     pub fn parse_expression(
         &mut self,
         expression: syntax_tree::Expr,
         parser_scope: &ParserScope,
-    ) -> execution_tree::Expr {
-        let inner = expression.into_inner();
-        match inner {
+    ) -> Result<execution_tree::Expr, Error> {
+        let (inner, span) = expression.into_parts();
+        self.parse_expression_inner(inner, parser_scope)
+            .map(|expression| expression.with_span(span.clone()))
+            .map_err(|error| error.at(self.sources.locate(&span)))
+    }
+
+    fn parse_expression_inner(
+        &mut self,
+        inner: syntax_tree::ExprInner,
+        parser_scope: &ParserScope,
+    ) -> Result<execution_tree::Expr, Error> {
+        let expression = match inner {
             syntax_tree::ExprInner::Scope(scope) => {
                 let parser_scope = parser_scope.child_common();
-                let scope = self.parse_syntax_tree_scope(scope, &parser_scope);
+                let scope = self.parse_syntax_tree_scope(scope, &parser_scope)?;
                 execution_tree::Expr::new_scope(scope)
             }
             syntax_tree::ExprInner::Literal(literal) => {
                 let literal = self.parse_literal(literal);
                 execution_tree::Expr::new_literal(literal)
             }
+            syntax_tree::ExprInner::List(list) => {
+                let list = self.parse_list(list, parser_scope)?;
+                execution_tree::Expr::new_list(list)
+            }
+            syntax_tree::ExprInner::Object(object) => {
+                let object = self.parse_object(object, parser_scope)?;
+                execution_tree::Expr::new_object(object)
+            }
+            syntax_tree::ExprInner::FieldCall(field_call) => {
+                let field_call = self.parse_field_call(field_call, parser_scope)?;
+                execution_tree::Expr::new_field_call(field_call)
+            }
+            syntax_tree::ExprInner::FieldAssign(field_assignment) => {
+                let field_assignment =
+                    self.parse_field_assignment(field_assignment, parser_scope)?;
+                execution_tree::Expr::new_field_assignment(field_assignment)
+            }
             syntax_tree::ExprInner::VarDef(variable_definition) => {
                 let variable_definition =
-                    self.parse_variable_definition(variable_definition, parser_scope);
+                    self.parse_variable_definition(variable_definition, parser_scope)?;
                 execution_tree::Expr::new_variable_definition(variable_definition)
             }
             syntax_tree::ExprInner::VarAssign(variable_assignment) => {
                 let variable_assignment =
-                    self.parse_variable_assignment(variable_assignment, parser_scope);
+                    self.parse_variable_assignment(variable_assignment, parser_scope)?;
                 execution_tree::Expr::new_variable_assignment(variable_assignment)
             }
             syntax_tree::ExprInner::VarCall(variable_call) => {
-                let variable_call = self.parse_variable_call(variable_call, parser_scope);
+                let variable_call = self.parse_variable_call(variable_call, parser_scope)?;
                 execution_tree::Expr::new_variable_call(variable_call)
             }
             syntax_tree::ExprInner::FnDef(function_definition) => {
                 let function_definition =
-                    self.parse_function_definition(function_definition, parser_scope);
+                    self.parse_function_definition(function_definition, parser_scope, None)?;
                 execution_tree::Expr::new_function_definition(function_definition)
             }
             syntax_tree::ExprInner::FnCall(function_call) => {
-                let function_call = self.parse_function_call(function_call, parser_scope);
+                let function_call = self.parse_function_call(function_call, parser_scope)?;
                 execution_tree::Expr::new_function_call(function_call)
             }
             syntax_tree::ExprInner::FnRet(function_return) => {
-                let function_return = self.parse_function_return(function_return, parser_scope);
+                let function_return = self.parse_function_return(function_return, parser_scope)?;
                 execution_tree::Expr::new_function_return(function_return)
             }
             syntax_tree::ExprInner::Loop(loop_) => {
-                let loop_ = self.parse_loop(loop_, parser_scope);
+                let loop_ = self.parse_loop(loop_, parser_scope)?;
                 execution_tree::Expr::new_loop(loop_)
             }
             syntax_tree::ExprInner::LoopBr(loop_break) => {
-                let loop_break = self.parse_loop_break(loop_break, parser_scope);
+                let loop_break = self.parse_loop_break(loop_break, parser_scope)?;
                 execution_tree::Expr::new_loop_break(loop_break)
             }
             syntax_tree::ExprInner::Cond(condition) => {
-                let condition = self.parse_condition(condition, parser_scope);
+                let condition = self.parse_condition(condition, parser_scope)?;
                 execution_tree::Expr::new_condition(condition)
             }
-        }
+            syntax_tree::ExprInner::Import(syntax_tree::Import { path }) => {
+                // imports are linked by the engine, which knows where scripts come from
+                return Err(Error::new_resolve(format!("unlinked import of '{path}'")));
+            }
+            syntax_tree::ExprInner::Global(syntax_tree::VarDef { name, value }) => {
+                let value = match value.into_parts() {
+                    (syntax_tree::ExprInner::Scope(scope), span) => {
+                        let parser_scope = parser_scope.child_module();
+                        let scope = self.parse_syntax_tree_scope(scope, &parser_scope)?;
+                        execution_tree::Expr::new_scope(scope).with_span(span)
+                    }
+                    (inner, span) => self
+                        .parse_expression_inner(inner, parser_scope)?
+                        .with_span(span),
+                };
+                let variable_id = parser_scope.add_global(name);
+                let variable_definition = execution_tree::VarDef { value, variable_id };
+                execution_tree::Expr::new_variable_definition(variable_definition)
+            }
+            syntax_tree::ExprInner::Test(syntax_tree::Test { name, .. }) => {
+                let message = format!("test '{name}' declared outside the top level");
+                return Err(Error::new_resolve(message));
+            }
+        };
+        Ok(expression)
     }
 
     pub fn parse_literal(&mut self, literal: syntax_tree::Literal) -> execution_tree::Literal {
@@ -150,47 +228,113 @@ impl Parser {
         execution_tree::Literal(value)
     }
 
+    pub fn parse_list(
+        &mut self,
+        list: syntax_tree::List,
+        parser_scope: &ParserScope,
+    ) -> Result<execution_tree::List, Error> {
+        let syntax_tree::List { elements } = list;
+        let elements = elements
+            .into_iter()
+            .map(|element| self.parse_expression(element, parser_scope))
+            .collect::<Result<_, _>>()?;
+        Ok(execution_tree::List { elements })
+    }
+
+    pub fn parse_object(
+        &mut self,
+        object: syntax_tree::Object,
+        parser_scope: &ParserScope,
+    ) -> Result<execution_tree::Object, Error> {
+        let syntax_tree::Object { fields } = object;
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| Ok((name, self.parse_expression(value, parser_scope)?)))
+            .collect::<Result<_, Error>>()?;
+        Ok(execution_tree::Object { fields })
+    }
+
+    pub fn parse_field_call(
+        &mut self,
+        field_call: syntax_tree::FieldCall,
+        parser_scope: &ParserScope,
+    ) -> Result<execution_tree::FieldCall, Error> {
+        let syntax_tree::FieldCall { object, field } = field_call;
+        let object = self.parse_expression(object, parser_scope)?;
+        Ok(execution_tree::FieldCall { object, field })
+    }
+
+    pub fn parse_field_assignment(
+        &mut self,
+        field_assignment: syntax_tree::FieldAssign,
+        parser_scope: &ParserScope,
+    ) -> Result<execution_tree::FieldAssign, Error> {
+        let syntax_tree::FieldAssign { name, path, value } = field_assignment;
+        let value = self.parse_expression(value, parser_scope)?;
+        let variable_id = parser_scope.get_variable_id(&name).ok_or_else(|| {
+            Error::new_resolve(format!("assignment to undefined variable '{name}'"))
+        })?;
+        Ok(execution_tree::FieldAssign {
+            variable_id,
+            path,
+            value,
+        })
+    }
+
     pub fn parse_variable_definition(
         &mut self,
         variable_definition: syntax_tree::VarDef,
         parser_scope: &ParserScope,
-    ) -> execution_tree::VarDef {
+    ) -> Result<execution_tree::VarDef, Error> {
         let syntax_tree::VarDef { name, value } = variable_definition;
-        let value = self.parse_expression(value, parser_scope);
+        let value = self.parse_expression(value, parser_scope)?;
         let variable_id = parser_scope.add_name(name);
-        execution_tree::VarDef { value, variable_id }
+        Ok(execution_tree::VarDef { value, variable_id })
     }
 
     pub fn parse_variable_assignment(
         &mut self,
         variable_assignment: syntax_tree::VarAssign,
         parser_scope: &ParserScope,
-    ) -> execution_tree::VarAssign {
+    ) -> Result<execution_tree::VarAssign, Error> {
         let syntax_tree::VarAssign { name, value } = variable_assignment;
-        let value = self.parse_expression(value, parser_scope);
-        let variable_id = parser_scope
-            .get_variable_id(&name)
-            .expect("assignment to undefined variable");
-        execution_tree::VarAssign { value, variable_id }
+        let variable_id = parser_scope.get_variable_id(&name).ok_or_else(|| {
+            Error::new_resolve(format!("assignment to undefined variable '{name}'"))
+        })?;
+        let value = match value.into_parts() {
+            // a function assigned to a variable, recursive when it calls that variable
+            (syntax_tree::ExprInner::FnDef(function_definition), span) => {
+                let function_definition = self
+                    .parse_function_definition(function_definition, parser_scope, Some(variable_id))
+                    .map_err(|error| error.at(self.sources.locate(&span)))?;
+                execution_tree::Expr::new_function_definition(function_definition).with_span(span)
+            }
+            (inner, span) => {
+                self.parse_expression(syntax_tree::Expr::new(inner).with_span(span), parser_scope)?
+            }
+        };
+        Ok(execution_tree::VarAssign { value, variable_id })
     }
 
     pub fn parse_variable_call(
         &mut self,
         variable_call: syntax_tree::VarCall,
         parser_scope: &ParserScope,
-    ) -> execution_tree::VarCall {
+    ) -> Result<execution_tree::VarCall, Error> {
         let syntax_tree::VarCall { name } = variable_call;
         let variable_id = parser_scope
             .get_variable_id(&name)
-            .expect("call of undefined variable");
-        execution_tree::VarCall { variable_id }
+            .ok_or_else(|| Error::new_resolve(format!("call of undefined variable '{name}'")))?;
+        Ok(execution_tree::VarCall { variable_id })
     }
 
+    /// Resolves a function, `self_id` being the variable it is assigned to, if any.
     pub fn parse_function_definition(
         &mut self,
         function_definition: syntax_tree::FnDef,
         parser_scope: &ParserScope,
-    ) -> execution_tree::FnDef {
+        self_id: Option<Id>,
+    ) -> Result<execution_tree::FnDef, Error> {
         let syntax_tree::FnDef {
             body,
             parameter_names,
@@ -201,104 +345,114 @@ impl Parser {
             .into_iter()
             .map(|name| parser_scope.add_name(name))
             .collect();
-        let body_scope_id = self.parse_syntax_tree_scope(body, &parser_scope);
+        let body_scope_id = self.parse_syntax_tree_scope(body, &parser_scope)?;
+        let mut captured_ids = parser_scope.captured_ids();
+        let self_id = self_id.filter(|self_id| captured_ids.contains(self_id));
+        captured_ids.retain(|id| Some(*id) != self_id);
 
-        execution_tree::FnDef {
+        Ok(execution_tree::FnDef {
             body_scope_id,
             parameter_ids,
-        }
+            captured_ids,
+            self_id,
+        })
     }
 
     pub fn parse_function_call(
         &mut self,
         function_call: syntax_tree::FnCall,
         parser_scope: &ParserScope,
-    ) -> execution_tree::FnCall {
-        let syntax_tree::FnCall { name, arguments } = function_call;
-
-        let variable_id = parser_scope
-            .get_variable_id(&name)
-            .unwrap_or_else(|| panic!("call of undeclared function '{name}'"));
+    ) -> Result<execution_tree::FnCall, Error> {
+        let function_name = function_call
+            .callee_name()
+            .unwrap_or("<anonymous>")
+            .to_string();
+        let syntax_tree::FnCall { callee, arguments } = function_call;
+
+        let callee = self.parse_expression(callee, parser_scope)?;
         let parameters = arguments
             .into_iter()
             .map(|argument| self.parse_expression(argument, parser_scope))
-            .collect();
+            .collect::<Result<_, _>>()?;
 
-        execution_tree::FnCall {
+        Ok(execution_tree::FnCall {
             arguments: parameters,
-            variable_id,
-        }
+            function_name,
+            callee,
+        })
     }
 
     pub fn parse_function_return(
         &mut self,
         function_return: syntax_tree::FnRet,
         parser_scope: &ParserScope,
-    ) -> execution_tree::FnRet {
+    ) -> Result<execution_tree::FnRet, Error> {
         let syntax_tree::FnRet { value } = function_return;
 
-        let value = self.parse_expression(value, parser_scope);
+        let value = self.parse_expression(value, parser_scope)?;
         let function_scope_id = parser_scope
             .get_current_function_id()
-            .expect("returning outside a function");
+            .ok_or_else(|| Error::new_resolve("returning outside a function"))?;
 
-        execution_tree::FnRet {
+        Ok(execution_tree::FnRet {
             value,
             function_scope_id,
-        }
+        })
     }
 
     pub fn parse_loop(
         &mut self,
         loop_: syntax_tree::Loop,
         parser_scope: &ParserScope,
-    ) -> execution_tree::Loop {
+    ) -> Result<execution_tree::Loop, Error> {
         let syntax_tree::Loop { body } = loop_;
 
         let parser_scope = parser_scope.child_loop();
-        let body_scope_id = self.parse_syntax_tree_scope(body, &parser_scope);
+        let body_scope_id = self.parse_syntax_tree_scope(body, &parser_scope)?;
 
-        execution_tree::Loop { body_scope_id }
+        Ok(execution_tree::Loop { body_scope_id })
     }
 
     pub fn parse_loop_break(
         &mut self,
         loop_break: syntax_tree::LoopBr,
         parser_scope: &ParserScope,
-    ) -> execution_tree::LoopBr {
+    ) -> Result<execution_tree::LoopBr, Error> {
         let syntax_tree::LoopBr { value } = loop_break;
 
-        let value = self.parse_expression(value, parser_scope);
+        let value = self.parse_expression(value, parser_scope)?;
         let loop_scope_id = parser_scope
             .get_current_loop_id()
-            .expect("breaking outside a loop");
+            .ok_or_else(|| Error::new_resolve("breaking outside a loop"))?;
 
-        execution_tree::LoopBr {
+        Ok(execution_tree::LoopBr {
             value,
             loop_scope_id,
-        }
+        })
     }
 
     pub fn parse_condition(
         &mut self,
         condition: syntax_tree::Cond,
         parser_scope: &ParserScope,
-    ) -> execution_tree::Cond {
+    ) -> Result<execution_tree::Cond, Error> {
         let syntax_tree::Cond {
             condition,
             arm_true,
             arm_false,
         } = condition;
 
-        let condition = self.parse_expression(condition, parser_scope);
-        let arm_true = self.parse_expression(arm_true, parser_scope);
-        let arm_false = arm_false.map(|arm_false| self.parse_expression(arm_false, parser_scope));
+        let condition = self.parse_expression(condition, parser_scope)?;
+        let arm_true = self.parse_expression(arm_true, parser_scope)?;
+        let arm_false = arm_false
+            .map(|arm_false| self.parse_expression(arm_false, parser_scope))
+            .transpose()?;
 
-        execution_tree::Cond {
+        Ok(execution_tree::Cond {
             condition,
             arm_true,
             arm_false,
-        }
+        })
     }
 }
 
@@ -306,10 +460,12 @@ impl Parser {
 struct ParserScopeVariables {
     parent_scope: Option<Rc<Mutex<ParserScopeVariables>>>,
     local_variables: HashMap<String, Id>,
+    /// For the main scope of a function, the variables of enclosing scopes it uses.
+    captured_ids: Option<Vec<Id>>,
 }
 
 impl ParserScopeVariables {
-    pub fn get_id(&self, name: &str) -> Option<Id> {
+    pub fn get_id(&mut self, name: &str) -> Option<Id> {
         self.get_id_in_local(name)
             .or_else(|| self.get_id_in_parents(name))
     }
@@ -318,16 +474,28 @@ impl ParserScopeVariables {
         self.local_variables.get(name).cloned()
     }
 
-    fn get_id_in_parents(&self, name: &str) -> Option<Id> {
-        self.parent_scope
+    fn get_id_in_parents(&mut self, name: &str) -> Option<Id> {
+        let id = self
+            .parent_scope
             .as_ref()
-            .and_then(|parent| parent.lock().unwrap().get_id(name))
+            .and_then(|parent| parent.lock().unwrap().get_id(name))?;
+        if let Some(captured_ids) = &mut self.captured_ids {
+            if !captured_ids.contains(&id) {
+                captured_ids.push(id);
+            }
+        }
+        Some(id)
     }
 
     fn local_variable_ids(&self) -> Vec<Id> {
         self.local_variables.values().cloned().collect()
     }
 
+    fn local_variables(&self) -> Vec<(String, Id)> {
+        let variables = self.local_variables.iter();
+        variables.map(|(name, id)| (name.clone(), *id)).collect()
+    }
+
     fn add_name(&mut self, name: String, id: Id) {
         let _dropped = self.local_variables.insert(name, id);
     }
@@ -342,6 +510,16 @@ pub struct ParserScope {
     parent_id: Option<Id>,
     current_function_scope_id: Option<Id>,
     current_loop_scope_id: Option<Id>,
+    /// The prelude values and modules defined so far, shared by every scope of a program.
+    globals: Rc<Mutex<HashMap<String, Id>>>,
+    /// Whether this is the main scope of a program or module, where tests are declared.
+    top_level: bool,
+}
+
+/// What `ParserScope::restore_bindings` restores.
+pub struct Bindings {
+    local_variables: HashMap<String, Id>,
+    globals: HashMap<String, Id>,
 }
 
 impl ParserScope {
@@ -350,6 +528,7 @@ impl ParserScope {
         let next_id = current_id.next();
         let variables = ParserScopeVariables {
             local_variables: HashMap::new(),
+            captured_ids: None,
             parent_scope: None,
         };
         Self {
@@ -359,12 +538,15 @@ impl ParserScope {
             variables: Rc::new(Mutex::new(variables)),
             current_function_scope_id: None,
             current_loop_scope_id: None,
+            globals: Rc::new(Mutex::new(HashMap::new())),
+            top_level: true,
         }
     }
 
     pub fn child_common(&self) -> Self {
         let variables = ParserScopeVariables {
             local_variables: HashMap::new(),
+            captured_ids: None,
             parent_scope: Some(self.variables.clone()),
         };
         Self {
@@ -374,12 +556,15 @@ impl ParserScope {
             current_id: self.request_new_id(),
             current_function_scope_id: self.get_current_function_id(),
             current_loop_scope_id: self.get_current_loop_id(),
+            globals: self.globals.clone(),
+            top_level: false,
         }
     }
 
     pub fn child_function(&self) -> Self {
         let variables = ParserScopeVariables {
             local_variables: HashMap::new(),
+            captured_ids: Some(Vec::new()),
             parent_scope: Some(self.variables.clone()),
         };
 
@@ -392,12 +577,15 @@ impl ParserScope {
             current_id,
             current_function_scope_id: Some(current_id),
             current_loop_scope_id: None,
+            globals: self.globals.clone(),
+            top_level: false,
         }
     }
 
     pub fn child_loop(&self) -> Self {
         let variables = ParserScopeVariables {
             local_variables: HashMap::new(),
+            captured_ids: None,
             parent_scope: Some(self.variables.clone()),
         };
 
@@ -410,6 +598,33 @@ impl ParserScope {
             current_id,
             current_function_scope_id: self.get_current_function_id(),
             current_loop_scope_id: Some(current_id),
+            globals: self.globals.clone(),
+            top_level: false,
+        }
+    }
+
+    /// The main scope of a module, seeing the globals defined so far but none of the names of
+    /// its importers.
+    pub fn child_module(&self) -> Self {
+        let globals = ParserScopeVariables {
+            local_variables: self.globals.lock().unwrap().clone(),
+            captured_ids: None,
+            parent_scope: None,
+        };
+        let variables = ParserScopeVariables {
+            local_variables: HashMap::new(),
+            captured_ids: None,
+            parent_scope: Some(Rc::new(Mutex::new(globals))),
+        };
+        Self {
+            parent_id: Some(self.get_current_id()),
+            next_id: self.next_id.clone(),
+            variables: Rc::new(Mutex::new(variables)),
+            current_id: self.request_new_id(),
+            current_function_scope_id: None,
+            current_loop_scope_id: None,
+            globals: self.globals.clone(),
+            top_level: true,
         }
     }
 
@@ -429,6 +644,10 @@ impl ParserScope {
         self.parent_id
     }
 
+    pub fn is_top_level(&self) -> bool {
+        self.top_level
+    }
+
     pub fn get_variable_id(&self, name: &str) -> Option<Id> {
         self.variables.lock().unwrap().get_id(name)
     }
@@ -446,6 +665,19 @@ impl ParserScope {
         new_id
     }
 
+    /// Defines a name in this scope which modules resolved later also see.
+    pub fn add_global(&self, name: String) -> Id {
+        let id = self.add_name(name.clone());
+        self.globals.lock().unwrap().insert(name, id);
+        id
+    }
+
+    /// The variables of enclosing scopes used by the function this is the main scope of.
+    pub fn captured_ids(&self) -> Vec<Id> {
+        let variables = self.variables.lock().unwrap();
+        variables.captured_ids.clone().unwrap_or_default()
+    }
+
     pub fn add_anonymous(&self) -> Id {
         self.request_new_id()
     }
@@ -453,4 +685,27 @@ impl ParserScope {
     pub fn local_variable_ids(&self) -> Vec<Id> {
         self.variables.lock().unwrap().local_variable_ids()
     }
+
+    /// The names defined in this scope, with their ids.
+    pub fn local_variables(&self) -> Vec<(String, Id)> {
+        self.variables.lock().unwrap().local_variables()
+    }
+
+    /// The names this scope and the globals bind, for `restore_bindings` to undo later
+    /// definitions.
+    pub fn bindings(&self) -> Bindings {
+        Bindings {
+            local_variables: self.variables.lock().unwrap().local_variables.clone(),
+            globals: self.globals.lock().unwrap().clone(),
+        }
+    }
+
+    pub fn restore_bindings(&self, bindings: Bindings) {
+        let Bindings {
+            local_variables,
+            globals,
+        } = bindings;
+        self.variables.lock().unwrap().local_variables = local_variables;
+        *self.globals.lock().unwrap() = globals;
+    }
 }
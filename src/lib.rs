@@ -0,0 +1,73 @@
+pub mod demo;
+pub mod engine;
+pub mod error;
+pub mod execution_tree;
+pub mod module;
+pub mod prelude;
+pub mod repl;
+pub mod runtime;
+pub mod source;
+pub mod syntax_tree;
+pub mod testing;
+pub mod value;
+
+pub use engine::Engine;
+pub use error::Error;
+pub use value::Value;
+
+#[test]
+fn it_works() {
+    use crate::execution_tree::parser::Parser;
+    use crate::prelude::std_prelude;
+    use crate::runtime::Runtime;
+    use crate::syntax_tree::*;
+
+    let ast = Program::new(Scope::new(vec![
+        Expr::new_variable_definition("a", Expr::new_literal(3.0)),
+        Expr::new_variable_assignment("a", Expr::new_literal(6.0)),
+        Expr::new_variable_definition(
+            "my_print",
+            Expr::new_function_definition(
+                vec!["to_print"],
+                Scope::new(vec![
+                    Expr::new_variable_definition("a", Expr::new_variable_call("to_print")),
+                    Expr::new_function_call("out", vec![Expr::new_variable_call("a")]),
+                ]),
+            ),
+        ),
+        Expr::new_function_call("my_print", vec!["hello, PORTE".into()]),
+        Expr::new_function_call("out", vec![Expr::new_variable_call("a")]),
+    ]));
+    let exec = Parser::parse(ast, std_prelude).unwrap();
+    let result = Runtime::new().execute(&exec).unwrap().into_value();
+    assert_eq!(result.as_number(), Some(6.));
+
+    let text = r#"a: 3; a <- 6;
+        my_print: (to_print) => { a: to_print; out(a) };
+        my_print("hello, PORTE");
+        out(a)"#;
+    let result = Engine::new().eval(text).unwrap();
+    assert_eq!(result.as_number(), Some(6.));
+}
+
+#[test]
+fn ast() {
+    use crate::execution_tree;
+    use crate::syntax_tree;
+
+    let text = r#"
+	a: 3;
+	{
+		a <- 4;
+		a: 5
+	}
+	"#;
+
+    let tree = syntax_tree::parser::ParserWrapper::new()
+        .parse(text)
+        .unwrap();
+    dbg!(&tree);
+
+    let executable = execution_tree::parser::Parser::parse(tree, |_| ()).unwrap();
+    dbg!(&executable);
+}
@@ -1,50 +1,85 @@
-use std::{env::args, fs};
+use std::{
+    env::{self, args},
+    io,
+    path::PathBuf,
+    process::exit,
+};
 
-pub mod demo;
-pub mod execution_tree;
-pub mod prelude;
-pub mod runtime;
-pub mod syntax_tree;
-pub mod value;
+use porte::{engine::exit_status, repl, testing::run_tests, Engine, Error};
 
-fn main() {
-    use prelude::std_prelude;
+/// Process status of a command line that cannot be understood.
+const USAGE_STATUS: i32 = 64;
 
-    let path = args().nth(1).expect("[error]: usage 'porte <path>'");
-    let input = fs::read_to_string(path).expect("file not found");
-    let ast_parser = syntax_tree::parser::ParserWrapper::new();
-    let parsed = ast_parser.parse(&input).unwrap();
-    let executable = execution_tree::parser::Parser::parse(parsed, std_prelude);
-    let mut runtime = runtime::Runtime::new();
-    runtime.execute(&executable);
-}
+const USAGE: &str = "\
+[error]: usage 'porte [-I <directory>]... [<path> [-- <args>...]]'
+            or 'porte test [-I <directory>]... [-f <filter>]... <path>...'";
 
-#[test]
-fn it_works() {
-    use crate::execution_tree::parser::Parser;
-    use crate::prelude::std_prelude;
-    use crate::runtime::Runtime;
-    use crate::syntax_tree::*;
+fn main() {
+    let mut args = args().skip(1).peekable();
+    let testing = args.next_if(|arg| arg == "test").is_some();
+    let mut search_path: Vec<PathBuf> = Vec::new();
+    let mut filters: Vec<String> = Vec::new();
+    let mut paths: Vec<PathBuf> = Vec::new();
+    loop {
+        match args.next().as_deref() {
+            Some("-I" | "--import-path") => match args.next() {
+                Some(directory) => search_path.push(directory.into()),
+                None => usage(),
+            },
+            Some("-f" | "--filter") if testing => match args.next() {
+                Some(filter) => filters.push(filter),
+                None => usage(),
+            },
+            Some(path) => {
+                paths.push(path.into());
+                // what follows the script belongs to it
+                if !testing {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    // directories from the command line are searched before the ones from the environment
+    if let Some(directories) = env::var_os("PORTE_PATH") {
+        search_path.extend(env::split_paths(&directories));
+    }
+    let mut script_args: Vec<_> = args.collect();
+    if script_args.first().is_some_and(|arg| arg == "--") {
+        script_args.remove(0);
+    }
 
-    let ast = Program {
-        body: Scope::new(vec![
-            Expr::new_variable_definition("a", Expr::new_literal(3.0)),
-            Expr::new_variable_assignment("a", Expr::new_literal(6.0)),
-            Expr::new_variable_definition(
-                "my_print",
-                Expr::new_function_definition(
-                    vec!["to_print"],
-                    Scope::new(vec![
-                        Expr::new_variable_definition("a", Expr::new_variable_call("to_print")),
-                        Expr::new_function_call("print", vec![Expr::new_variable_call("a")]),
-                    ]),
-                ),
-            ),
-            Expr::new_function_call("my_print", vec!["hello, PORTE".into()]),
-            Expr::new_function_call("print", vec![Expr::new_variable_call("a")]),
-        ]),
+    let mut engine = Engine::with_args(script_args);
+    for directory in search_path {
+        engine.search_path(directory);
+    }
+    if testing {
+        if paths.is_empty() {
+            usage();
+        }
+        match run_tests(&engine, &paths, &filters, &mut io::stdout()) {
+            Ok(summary) if summary.failed == 0 => exit(0),
+            Ok(_) => exit(1),
+            Err(error) => {
+                eprintln!("[error]: cannot report tests: {error}");
+                exit(1);
+            }
+        }
+    }
+    // without a script, inputs are read interactively
+    let Some(path) = paths.pop() else {
+        exit(repl::run(&engine));
     };
-    let exec = Parser::parse(ast, std_prelude);
-    println!("\n\n\n-- running: --");
-    let _result = Runtime::new().execute(&exec);
+    let result = engine.eval_file(path);
+    if let Err(error) = &result {
+        if !matches!(error.inner(), Error::Exit(_)) {
+            eprintln!("{error}");
+        }
+    }
+    exit(exit_status(&result));
+}
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    exit(USAGE_STATUS);
 }
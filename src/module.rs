@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fs, mem,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::Error,
+    source::{Source, Sources},
+    syntax_tree::{
+        parser::ParserWrapper, Expr, ExprInner, Import, Program, Scope, VarCall, VarDef,
+    },
+};
+
+/// Replaces the imports of a program by the modules they refer to.
+///
+/// Every module is parsed once and defined before the program, after the modules it imports, as
+/// a hidden global holding the object of its top-level definitions. Linking programs one after
+/// the other with the same linker reuses the modules linked for the previous ones.
+pub struct Linker<'a> {
+    parser: &'a ParserWrapper,
+    search_path: &'a [PathBuf],
+    sources: Sources,
+    definitions: Vec<Expr>,
+    linked: HashMap<PathBuf, String>,
+    /// Modules being linked, outermost first, with the names they were imported as.
+    loading: Vec<(PathBuf, String)>,
+}
+
+impl<'a> Linker<'a> {
+    pub fn new(parser: &'a ParserWrapper, search_path: &'a [PathBuf]) -> Self {
+        Self {
+            parser,
+            search_path,
+            sources: Sources::default(),
+            definitions: Vec::new(),
+            linked: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+
+    /// The modules linked so far, for `restore_linked` to forget those linked later.
+    pub fn linked(&self) -> HashMap<PathBuf, String> {
+        self.linked.clone()
+    }
+
+    pub fn restore_linked(&mut self, linked: HashMap<PathBuf, String>) {
+        self.linked = linked;
+    }
+
+    /// Links a program whose relative imports start from `directory`.
+    pub fn link(&mut self, program: Program, directory: &Path) -> Result<Program, Error> {
+        let Program { mut body, sources } = program;
+        self.sources = sources;
+        let linked = self.link_scope(&mut body, directory);
+        let sources = mem::take(&mut self.sources);
+        let definitions = mem::take(&mut self.definitions);
+        linked?;
+        body.instructions.splice(0..0, definitions);
+        Ok(Program { body, sources })
+    }
+
+    fn link_scope(&mut self, scope: &mut Scope, directory: &Path) -> Result<(), Error> {
+        for instruction in &mut scope.instructions {
+            self.link_expression(instruction, directory)?;
+        }
+        Ok(())
+    }
+
+    fn link_expression(&mut self, expression: &mut Expr, directory: &Path) -> Result<(), Error> {
+        match expression.inner.as_mut() {
+            ExprInner::Scope(scope) => self.link_scope(scope, directory)?,
+            ExprInner::Literal(_) | ExprInner::VarCall(_) => (),
+            ExprInner::List(list) => {
+                for element in &mut list.elements {
+                    self.link_expression(element, directory)?;
+                }
+            }
+            ExprInner::Object(object) => {
+                for (_, value) in &mut object.fields {
+                    self.link_expression(value, directory)?;
+                }
+            }
+            ExprInner::FieldCall(field_call) => {
+                self.link_expression(&mut field_call.object, directory)?
+            }
+            ExprInner::FieldAssign(field_assignment) => {
+                self.link_expression(&mut field_assignment.value, directory)?
+            }
+            ExprInner::VarDef(variable_definition) | ExprInner::Global(variable_definition) => {
+                self.link_expression(&mut variable_definition.value, directory)?
+            }
+            ExprInner::VarAssign(variable_assignment) => {
+                self.link_expression(&mut variable_assignment.value, directory)?
+            }
+            ExprInner::FnDef(function_definition) => {
+                self.link_scope(&mut function_definition.body, directory)?
+            }
+            ExprInner::FnCall(function_call) => {
+                self.link_expression(&mut function_call.callee, directory)?;
+                for argument in &mut function_call.arguments {
+                    self.link_expression(argument, directory)?;
+                }
+            }
+            ExprInner::FnRet(function_return) => {
+                self.link_expression(&mut function_return.value, directory)?
+            }
+            ExprInner::Loop(loop_) => self.link_scope(&mut loop_.body, directory)?,
+            ExprInner::Test(test) => self.link_scope(&mut test.body, directory)?,
+            ExprInner::LoopBr(loop_break) => {
+                self.link_expression(&mut loop_break.value, directory)?
+            }
+            ExprInner::Cond(condition) => {
+                self.link_expression(&mut condition.condition, directory)?;
+                self.link_expression(&mut condition.arm_true, directory)?;
+                if let Some(arm_false) = &mut condition.arm_false {
+                    self.link_expression(arm_false, directory)?;
+                }
+            }
+            ExprInner::Import(Import { path }) => {
+                let name = self
+                    .import(path, directory)
+                    .map_err(|error| error.at(self.sources.locate(&expression.span)))?;
+                *expression.inner = ExprInner::VarCall(VarCall { name });
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks for the module next to the importing file, then in the search path.
+    fn resolve(&self, path: &str, directory: &Path) -> Result<PathBuf, Error> {
+        [directory]
+            .into_iter()
+            .chain(self.search_path.iter().map(PathBuf::as_path))
+            .map(|directory| directory.join(path))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| Error::new_resolve(format!("cannot find module '{path}'")))
+    }
+
+    /// Links a module unless it already was, returning the name of its definition.
+    fn import(&mut self, path: &str, directory: &Path) -> Result<String, Error> {
+        let path = self.resolve(path, directory)?;
+        let display_name = path.display().to_string();
+        let canonical = path.canonicalize()?;
+        if let Some(name) = self.linked.get(&canonical) {
+            return Ok(name.clone());
+        }
+        if let Some(start) = self.loading.iter().position(|(path, _)| *path == canonical) {
+            let cycle: Vec<_> = self.loading[start..]
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .chain([display_name.as_str()])
+                .collect();
+            let message = format!("import cycle: {}", cycle.join(" -> "));
+            return Err(Error::new_resolve(message));
+        }
+
+        let text = fs::read_to_string(&path)?;
+        let source = Source::new(&display_name, text).with_offset(self.sources.end());
+        let Program { mut body, sources } = self.parser.parse_source(source)?;
+        self.sources.append(sources);
+        self.loading.push((canonical.clone(), display_name.clone()));
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let linked = self.link_scope(&mut body, directory);
+        self.loading.pop();
+        linked?;
+
+        let mut exported: Vec<String> = Vec::new();
+        for instruction in &body.instructions {
+            if let ExprInner::VarDef(VarDef { name, .. }) = instruction.inner() {
+                exported.retain(|exported| exported != name);
+                exported.push(name.clone());
+            }
+        }
+        let fields = exported
+            .into_iter()
+            .map(|name| (name.clone(), Expr::new_variable_call(name)))
+            .collect();
+        body.instructions.push(Expr::new_object(fields));
+
+        // not a valid porte name, so scripts cannot shadow it
+        let name = format!("<module {display_name}>");
+        let definition = Expr::new_global(&name, body.into());
+        self.definitions.push(definition);
+        self.linked.insert(canonical, name.clone());
+        Ok(name)
+    }
+}
+
+/// Where scripts from a source import relative paths from.
+pub fn source_directory(source: &Source) -> PathBuf {
+    let directory = Path::new(source.name()).parent();
+    directory.unwrap_or(Path::new("")).to_path_buf()
+}
+
+#[test]
+fn test_linker_shares_modules() {
+    let root = std::env::temp_dir().join(format!("porte-linker-{}", std::process::id()));
+    fs::create_dir_all(root.join("lib")).unwrap();
+    fs::write(root.join("lib/a.pr"), r#"b: import "b.pr"; x: 1"#).unwrap();
+    fs::write(root.join("lib/b.pr"), "y: 2").unwrap();
+    let parser = ParserWrapper::new();
+    let main = Source::new(
+        root.join("main.pr").display(),
+        r#"import "lib/a.pr"; import "lib/b.pr""#,
+    );
+    let program = parser.parse_source(main).unwrap();
+    let program = Linker::new(&parser, &[]).link(program, &root).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+
+    let names: Vec<_> = program
+        .body
+        .instructions
+        .iter()
+        .map(|instruction| match instruction.inner() {
+            ExprInner::Global(VarDef { name, .. }) => name.clone(),
+            ExprInner::VarCall(VarCall { name }) => name.clone(),
+            _ => panic!("expected definitions then imports"),
+        })
+        .collect();
+    let b = format!("<module {}>", root.join("lib/b.pr").display());
+    let a = format!("<module {}>", root.join("lib/a.pr").display());
+    assert_eq!(names, [b.clone(), a.clone(), a, b]);
+}
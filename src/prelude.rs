@@ -1,148 +1,555 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 use crate::{
+    engine::is_script_status,
+    error::Error,
     execution_tree::parser::ParserBuilder,
-    value::{function::Function, Value},
+    value::{function::Function, ObjectRef, Value},
 };
 
+mod assert;
+mod bytes;
+mod env;
+mod fs;
+mod list;
+mod path;
+mod process;
+mod walk;
+
+pub(crate) use fs::io_error;
+
 pub fn std_prelude(builder: &mut ParserBuilder) {
-    type FunctOper = fn(Vec<Value>) -> Value;
-    let functions: Vec<(_, _, FunctOper)> = vec![
-        ("out", 1, out),
-        ("add", 2, add),
-        ("sub", 2, sub),
-        ("eq", 2, eq),
-        ("sup", 2, sup),
-        ("inf", 2, inf),
-        ("and", 2, and),
-        ("or", 2, or),
-        ("not", 1, not),
-        ("str", 2, str),
-        ("obj", 0, obj),
-        ("set", 3, set),
-        ("get", 2, get),
-    ];
-
-    for (name, arg_count, closure) in functions {
+    register(
+        builder,
+        &[
+            ("out", 1, out),
+            ("exit", 1, exit),
+            ("add", 2, add),
+            ("sub", 2, sub),
+            ("mul", 2, mul),
+            ("div", 2, div),
+            ("mod", 2, modulo),
+            ("floor", 1, floor),
+            ("ceil", 1, ceil),
+            ("round", 1, round),
+            ("int", 1, int),
+            ("float", 1, float),
+            ("eq", 2, eq),
+            ("sup", 2, sup),
+            ("inf", 2, inf),
+            ("and", 2, and),
+            ("or", 2, or),
+            ("not", 1, not),
+            ("str", 1, str),
+            ("obj", 0, obj),
+            ("set", 3, set),
+            ("get", 2, get),
+            ("del", 2, del),
+            ("has", 2, has),
+            ("clone", 1, clone),
+            ("deep_copy", 1, deep_copy),
+        ],
+    );
+    assert::assert_prelude(builder);
+    list::list_prelude(builder);
+    bytes::bytes_prelude(builder);
+    fs::fs_prelude(builder);
+    path::path_prelude(builder);
+    walk::walk_prelude(builder);
+    process::process_prelude(builder);
+    env::env_prelude(builder);
+}
+
+type FunctOper = fn(Vec<Value>) -> Result<Value, Error>;
+
+/// Adds native functions to the prelude, by name and argument count.
+fn register(builder: &mut ParserBuilder, functions: &[(&str, usize, FunctOper)]) {
+    for &(name, arg_count, closure) in functions {
         builder.prelude(name.into(), Function::new_native(arg_count, closure).into());
     }
 }
 
-fn out(args: Vec<Value>) -> Value {
-    let to_print = args.get(0).unwrap();
-    let str = value_to_string(to_print);
+/// Exposes the command-line arguments of a script as the `args` list.
+pub fn args_prelude(args: Vec<String>) -> impl Fn(&mut ParserBuilder) {
+    let args: Vec<Value> = args.into_iter().map(Value::from).collect();
+    move |builder| builder.prelude("args".into(), args.clone().into())
+}
+
+/// Destructures the arguments of a native function, the runtime already checks arity.
+fn arguments<const N: usize>(args: Vec<Value>) -> Result<[Value; N], Error> {
+    args.try_into()
+        .map_err(|args: Vec<Value>| Error::new_arity(N, args.len()))
+}
+
+fn out(args: Vec<Value>) -> Result<Value, Error> {
+    let [to_print] = arguments(args)?;
+    let str = value_to_string(&to_print);
     println!("{str}");
-    to_print.clone()
+    Ok(to_print)
+}
+
+/// Operands of an arithmetic function, promoted to floats unless both are integers.
+enum Numbers {
+    Int(i64, i64),
+    Float(f64, f64),
 }
 
-fn add(args: Vec<Value>) -> Value {
-    let lhs = args.get(0).unwrap();
-    let rhs = args.get(1).unwrap();
+fn numbers(lhs: &Value, rhs: &Value) -> Option<Numbers> {
     match (lhs, rhs) {
-        (Value::Number(l), Value::Number(r)) => (l + r).into(),
-        (Value::String(l), Value::String(r)) => format!("{l}{r}").into(),
-        (Value::Number(l), Value::String(r)) => format!("{l}{r}").into(),
-        (Value::String(l), Value::Number(r)) => format!("{l}{r}").into(),
-        _ => panic!("adding incompatible types"),
+        (Value::Int(l), Value::Int(r)) => Some(Numbers::Int(*l, *r)),
+        _ => Some(Numbers::Float(lhs.as_number()?, rhs.as_number()?)),
     }
 }
 
-fn sub(args: Vec<Value>) -> Value {
-    let lhs = args.get(0).unwrap();
-    let rhs = args.get(1).unwrap();
-    match (lhs, rhs) {
-        (Value::Number(l), Value::Number(r)) => (l - r).into(),
-        _ => panic!("substracting non-numbers"),
+fn overflow(function: &str) -> Error {
+    Error::new_native_call(format!("integer overflow in '{function}'"))
+}
+
+/// Stops the script, the process exiting with that status.
+fn exit(args: Vec<Value>) -> Result<Value, Error> {
+    let [code] = arguments(args)?;
+    match code {
+        Value::Int(code) if is_script_status(code) => Err(Error::Exit(code as i32)),
+        Value::Int(code) => Err(Error::new_native_call(format!(
+            "'exit' cannot use status {code}, reserved or out of range"
+        ))),
+        other => Err(unexpected_type("int", &other, "exit")),
     }
 }
 
-fn eq(args: Vec<Value>) -> Value {
-    let lhs = args.get(0).unwrap();
-    let rhs = args.get(1).unwrap();
-    match (lhs, rhs) {
-        (Value::Bool(l), Value::Bool(r)) => (l == r).into(),
-        (Value::Number(l), Value::Number(r)) => (l == r).into(),
-        (Value::String(l), Value::String(r)) => (l == r).into(),
-        _ => false.into(),
+fn add(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    match numbers(&lhs, &rhs) {
+        Some(Numbers::Int(l, r)) => l
+            .checked_add(r)
+            .map(Value::from)
+            .ok_or_else(|| overflow("add")),
+        Some(Numbers::Float(l, r)) => Ok((l + r).into()),
+        None => match (lhs, rhs) {
+            (Value::String(l), Value::String(r)) => Ok(format!("{l}{r}").into()),
+            (l @ (Value::Int(_) | Value::Number(_)), Value::String(r)) => {
+                Ok(format!("{}{r}", value_to_string(&l)).into())
+            }
+            (Value::String(l), r @ (Value::Int(_) | Value::Number(_))) => {
+                Ok(format!("{l}{}", value_to_string(&r)).into())
+            }
+            (l, r) => Err(Error::new_type(format!(
+                "adding incompatible types {} and {}",
+                l.type_name(),
+                r.type_name()
+            ))),
+        },
     }
 }
 
-fn sup(args: Vec<Value>) -> Value {
-    let lhs = args.get(0).unwrap();
-    let rhs = args.get(1).unwrap();
-    match (lhs, rhs) {
-        (Value::Number(l), Value::Number(r)) => (l > r).into(),
-        _ => panic!("comparing non-numeric"),
+fn sub(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    match numbers(&lhs, &rhs) {
+        Some(Numbers::Int(l, r)) => l
+            .checked_sub(r)
+            .map(Value::from)
+            .ok_or_else(|| overflow("sub")),
+        Some(Numbers::Float(l, r)) => Ok((l - r).into()),
+        None => Err(Error::new_type("substracting non-numbers")),
+    }
+}
+
+fn mul(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    match numbers(&lhs, &rhs) {
+        Some(Numbers::Int(l, r)) => l
+            .checked_mul(r)
+            .map(Value::from)
+            .ok_or_else(|| overflow("mul")),
+        Some(Numbers::Float(l, r)) => Ok((l * r).into()),
+        None => Err(Error::new_type("multiplying non-numbers")),
+    }
+}
+
+/// Integer division truncates toward zero, like in Rust.
+fn div(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    match numbers(&lhs, &rhs) {
+        Some(Numbers::Int(_, 0)) => Err(Error::new_native_call("division by zero")),
+        Some(Numbers::Int(l, r)) => l
+            .checked_div(r)
+            .map(Value::from)
+            .ok_or_else(|| overflow("div")),
+        Some(Numbers::Float(l, r)) => Ok((l / r).into()),
+        None => Err(Error::new_type("dividing non-numbers")),
+    }
+}
+
+fn modulo(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    match numbers(&lhs, &rhs) {
+        Some(Numbers::Int(_, 0)) => Err(Error::new_native_call("division by zero")),
+        Some(Numbers::Int(l, r)) => l
+            .checked_rem(r)
+            .map(Value::from)
+            .ok_or_else(|| overflow("mod")),
+        Some(Numbers::Float(l, r)) => Ok((l % r).into()),
+        None => Err(Error::new_type("taking the remainder of non-numbers")),
     }
 }
 
-fn inf(args: Vec<Value>) -> Value {
-    let lhs = args.get(0).unwrap();
-    let rhs = args.get(1).unwrap();
+fn floor(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    rounded(input, f64::floor, "floor")
+}
+
+fn ceil(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    rounded(input, f64::ceil, "ceil")
+}
+
+/// Rounds half away from zero.
+fn round(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    rounded(input, f64::round, "round")
+}
+
+fn rounded(input: Value, rounding: fn(f64) -> f64, function: &str) -> Result<Value, Error> {
+    match input {
+        Value::Int(n) => Ok(n.into()),
+        Value::Number(n) => float_to_int(rounding(n), function),
+        other => Err(unexpected_type("number", &other, function)),
+    }
+}
+
+fn float_to_int(n: f64, function: &str) -> Result<Value, Error> {
+    // `i64::MAX as f64` rounds up to 2^63, which is out of range
+    if n.is_finite() && n >= i64::MIN as f64 && n < i64::MAX as f64 {
+        Ok((n as i64).into())
+    } else {
+        Err(Error::new_native_call(format!(
+            "'{function}' cannot represent {n} as an integer"
+        )))
+    }
+}
+
+/// Converts a number, truncating toward zero, or parses a string.
+fn int(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    match input {
+        Value::Int(n) => Ok(n.into()),
+        Value::Number(n) => float_to_int(n.trunc(), "int"),
+        Value::String(s) => s
+            .trim()
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| Error::new_native_call(format!("'int' cannot parse '{s}'"))),
+        other => Err(unexpected_type("number or string", &other, "int")),
+    }
+}
+
+fn float(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    match input {
+        Value::Int(n) => Ok((n as f64).into()),
+        Value::Number(n) => Ok(n.into()),
+        Value::String(s) => s
+            .trim()
+            .parse::<f64>()
+            .map(Value::from)
+            .map_err(|_| Error::new_native_call(format!("'float' cannot parse '{s}'"))),
+        other => Err(unexpected_type("number or string", &other, "float")),
+    }
+}
+
+fn eq(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    Ok(equals(&lhs, &rhs).into())
+}
+
+fn equals(lhs: &Value, rhs: &Value) -> bool {
     match (lhs, rhs) {
-        (Value::Number(l), Value::Number(r)) => (l < r).into(),
-        _ => panic!("comparing non-numeric"),
+        (Value::None, Value::None) => true,
+        (Value::Bool(l), Value::Bool(r)) => l == r,
+        (Value::String(l), Value::String(r)) => l == r,
+        (Value::Bytes(l), Value::Bytes(r)) => l == r,
+        (Value::List(l), Value::List(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| equals(l, r))
+        }
+        (Value::Object(l), Value::Object(r)) => Rc::ptr_eq(l, r),
+        (l, r) => match numbers(l, r) {
+            Some(Numbers::Int(l, r)) => l == r,
+            Some(Numbers::Float(l, r)) => l == r,
+            None => false,
+        },
+    }
+}
+
+fn sup(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    match numbers(&lhs, &rhs) {
+        Some(Numbers::Int(l, r)) => Ok((l > r).into()),
+        Some(Numbers::Float(l, r)) => Ok((l > r).into()),
+        None => Err(Error::new_type("comparing non-numeric")),
     }
 }
 
-fn and(args: Vec<Value>) -> Value {
-    let lhs = args.get(0).unwrap();
-    let rhs = args.get(1).unwrap();
+fn inf(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    match numbers(&lhs, &rhs) {
+        Some(Numbers::Int(l, r)) => Ok((l < r).into()),
+        Some(Numbers::Float(l, r)) => Ok((l < r).into()),
+        None => Err(Error::new_type("comparing non-numeric")),
+    }
+}
+
+fn and(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
     match (lhs, rhs) {
-        (Value::Bool(l), Value::Bool(r)) => (*l && *r).into(),
-        _ => panic!("intersection of non-boolean"),
+        (Value::Bool(l), Value::Bool(r)) => Ok((l && r).into()),
+        _ => Err(Error::new_type("intersection of non-boolean")),
     }
 }
 
-fn or(args: Vec<Value>) -> Value {
-    let lhs = args.get(0).unwrap();
-    let rhs = args.get(1).unwrap();
+fn or(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
     match (lhs, rhs) {
-        (Value::Bool(l), Value::Bool(r)) => (*l || *r).into(),
-        _ => panic!("union of non-boolean"),
+        (Value::Bool(l), Value::Bool(r)) => Ok((l || r).into()),
+        _ => Err(Error::new_type("union of non-boolean")),
     }
 }
 
-fn not(args: Vec<Value>) -> Value {
-    let input = args.get(0).unwrap();
-    let result = !input.as_bool().expect("complementing non-bool");
-    result.into()
+fn not(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    let input = input
+        .as_bool()
+        .ok_or_else(|| Error::new_type("complementing non-bool"))?;
+    Ok((!input).into())
 }
 
 fn value_to_string(input: &Value) -> String {
     match input {
         Value::None => "None".to_string(),
         Value::Bool(b) => format!("{b}"),
-        Value::Number(n) => format!("{n}"),
+        Value::Int(n) => format!("{n}"),
+        // unlike `Display`, `Debug` keeps the fraction of integral floats
+        Value::Number(n) => format!("{n:?}"),
         Value::String(s) => s.clone(),
+        Value::Bytes(bytes) => format!("<{} bytes>", bytes.len()),
+        Value::List(items) => {
+            let items: Vec<_> = items.iter().map(value_to_string).collect();
+            format!("[{}]", items.join(", "))
+        }
         Value::Object(_) => "[object]".into(),
         Value::Function(_) => "[function]".into(),
     }
 }
 
-fn str(args: Vec<Value>) -> Value {
-    let input = args.get(0).unwrap();
-    value_to_string(input).into()
+fn str(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    Ok(value_to_string(&input).into())
 }
 
-fn obj(_: Vec<Value>) -> Value {
-    Value::Object(HashMap::new())
+fn obj(_: Vec<Value>) -> Result<Value, Error> {
+    Ok(HashMap::new().into())
 }
 
-fn set(args: Vec<Value>) -> Value {
-    let mut object = args.get(0).unwrap().as_object().unwrap().clone();
-    let name = args.get(1).unwrap().as_string().unwrap().to_string();
-    let value = args.get(2).unwrap().clone();
-    if let Value::Bool(false) = value {
-        object.remove(&name);
-    } else {
-        object.insert(name, value);
+/// Updates the object in place and returns it.
+fn set(args: Vec<Value>) -> Result<Value, Error> {
+    let [object, name, value] = arguments(args)?;
+    let fields = expect_object(object.clone(), "set")?;
+    let name = expect_string(name, "set")?;
+    fields.borrow_mut().insert(name, value);
+    Ok(object)
+}
+
+/// Reads a field, `none` when the object does not have it.
+fn get(args: Vec<Value>) -> Result<Value, Error> {
+    let [object, name] = arguments(args)?;
+    let object = expect_object(object, "get")?;
+    let name = expect_string(name, "get")?;
+    let value = object.borrow().get(&name).cloned();
+    Ok(value.into())
+}
+
+/// Removes a field in place and returns the object.
+fn del(args: Vec<Value>) -> Result<Value, Error> {
+    let [object, name] = arguments(args)?;
+    let fields = expect_object(object.clone(), "del")?;
+    let name = expect_string(name, "del")?;
+    fields.borrow_mut().remove(&name);
+    Ok(object)
+}
+
+fn has(args: Vec<Value>) -> Result<Value, Error> {
+    let [object, name] = arguments(args)?;
+    let object = expect_object(object, "has")?;
+    let name = expect_string(name, "has")?;
+    let has = object.borrow().contains_key(&name);
+    Ok(has.into())
+}
+
+/// A new object with the same fields, which are still shared if they are objects.
+fn clone(args: Vec<Value>) -> Result<Value, Error> {
+    let [object] = arguments(args)?;
+    let object = expect_object(object, "clone")?;
+    let fields = object.borrow().clone();
+    Ok(fields.into())
+}
+
+/// A copy sharing no object with the original, aliases within it being preserved.
+fn deep_copy(args: Vec<Value>) -> Result<Value, Error> {
+    let [value] = arguments(args)?;
+    Ok(copy_value(&value, &mut HashMap::new()))
+}
+
+fn copy_value(value: &Value, copies: &mut HashMap<*const (), ObjectRef>) -> Value {
+    match value {
+        Value::List(items) => items
+            .iter()
+            .map(|item| copy_value(item, copies))
+            .collect::<Vec<_>>()
+            .into(),
+        Value::Object(object) => {
+            let key = Rc::as_ptr(object) as *const ();
+            if let Some(copy) = copies.get(&key) {
+                return Value::Object(copy.clone());
+            }
+            let copy = ObjectRef::default();
+            copies.insert(key, copy.clone());
+            let fields = object
+                .borrow()
+                .iter()
+                .map(|(name, field)| (name.clone(), copy_value(field, copies)))
+                .collect();
+            *copy.borrow_mut() = fields;
+            Value::Object(copy)
+        }
+        other => other.clone(),
+    }
+}
+
+fn expect_object(value: Value, function: &str) -> Result<ObjectRef, Error> {
+    match value {
+        Value::Object(object) => Ok(object),
+        other => Err(unexpected_type("object", &other, function)),
+    }
+}
+
+fn expect_bool(value: Value, function: &str) -> Result<bool, Error> {
+    match value {
+        Value::Bool(bool) => Ok(bool),
+        other => Err(unexpected_type("bool", &other, function)),
+    }
+}
+
+fn expect_bytes(value: Value, function: &str) -> Result<Vec<u8>, Error> {
+    match value {
+        Value::Bytes(bytes) => Ok(bytes),
+        other => Err(unexpected_type("bytes", &other, function)),
     }
-    object.into()
 }
 
-fn get(args: Vec<Value>) -> Value {
-    let object = args.get(0).unwrap().as_object().unwrap();
-    let name = args.get(1).unwrap().as_string().unwrap();
-    object.get(name).cloned().unwrap_or_else(|| false.into())
+fn expect_list(value: Value, function: &str) -> Result<Vec<Value>, Error> {
+    match value {
+        Value::List(list) => Ok(list),
+        other => Err(unexpected_type("list", &other, function)),
+    }
+}
+
+fn expect_function(value: Value, function: &str) -> Result<Function, Error> {
+    match value {
+        Value::Function(callee) => Ok(callee),
+        other => Err(unexpected_type("function", &other, function)),
+    }
+}
+
+fn expect_string(value: Value, function: &str) -> Result<String, Error> {
+    match value {
+        Value::String(string) => Ok(string),
+        other => Err(unexpected_type("string", &other, function)),
+    }
+}
+
+fn unexpected_type(expected: &str, found: &Value, function: &str) -> Error {
+    let found = found.type_name();
+    Error::new_type(format!("'{function}' expected {expected}, found {found}"))
+}
+
+#[test]
+fn test_add_incompatible_types() {
+    let result = add(vec![true.into(), 1.0.into()]);
+    assert!(matches!(result, Err(Error::Type(_))));
+}
+
+#[test]
+fn test_integers() {
+    use crate::engine::{eval, Engine};
+    let engine = Engine::new();
+    assert_eq!(
+        eval("9007199254740993 + 1").as_int(),
+        Some(9007199254740994)
+    );
+    assert_eq!(eval("7 / 2 * 2 + 7 % 2 - -1").as_int(), Some(8));
+    assert_eq!(eval("7 / 2.0").as_number(), Some(3.5));
+    assert!(eval("3 == 3.0 && 2 < 2.5").as_bool().unwrap());
+    assert_eq!(eval("str(3) + str(3.5)").as_string(), Some("33.5"));
+    assert_eq!(
+        eval("str([6 / 2.0, 3, -0.0])").as_string(),
+        Some("[3.0, 3, -0.0]")
+    );
+    let text = r#"str([floor(-1.5), ceil(1.2), round(2.5), int(-2.7), int(" 42 ")])"#;
+    assert_eq!(eval(text).as_string(), Some("[-2, 2, 3, -2, 42]"));
+    assert_eq!(eval("float(1) / 4").as_number(), Some(0.25));
+    let error = engine.eval("1 / 0").unwrap_err();
+    assert!(matches!(error.inner(), Error::NativeCall(_)));
+    assert!(engine.eval("9223372036854775807 + 1").is_err());
+}
+
+#[test]
+fn test_deep_copy_cycle() {
+    let object: Value = HashMap::new().into();
+    set(vec![object.clone(), "self".into(), object.clone()]).unwrap();
+    let copy = deep_copy(vec![object.clone()]).unwrap();
+    let inner = get(vec![copy.clone(), "self".into()]).unwrap();
+    assert!(equals(&copy, &inner));
+    assert!(!equals(&copy, &object));
+}
+
+#[test]
+fn test_object_aliasing() {
+    use crate::engine::eval;
+    let text = "a: { n: 1 }; b: a; b.n <- 2; set(a, \"m\", 3); a.n * 10 + b.m";
+    assert_eq!(eval(text).as_number().unwrap(), 23.);
+    let text =
+        "a: { inner: { n: 1 } }; b: clone(a); b.inner.n <- 2; b.top <- 3; str([a.inner.n, a.top])";
+    assert_eq!(eval(text).as_string().unwrap(), "[2, None]");
+    let text = "a: { inner: { n: 1 } }; b: deep_copy(a); b.inner.n <- 2; a.inner.n";
+    assert_eq!(eval(text).as_number().unwrap(), 1.);
+    let text = "bump: (o) => { o.n <- o.n + 1 }; o: { n: 0 }; map([1, 2], (x) => { bump(o) }); o.n";
+    assert_eq!(eval(text).as_number().unwrap(), 2.);
+    assert!(eval("a: {x: 1}; a == a && a != {x: 1}").as_bool().unwrap());
+}
+
+#[test]
+fn test_equals_none() {
+    let missing = get(vec![HashMap::new().into(), "key".into()]).unwrap();
+    assert!(equals(&missing, &Value::None));
+    assert!(!equals(&Value::None, &false.into()));
+    assert!(!equals(&0.into(), &Value::None));
+}
+
+#[test]
+fn test_get_non_object() {
+    let result = get(vec![1.0.into(), "key".into()]);
+    assert!(matches!(result, Err(Error::Type(_))));
+}
+
+#[test]
+fn test_object_keys() {
+    use crate::engine::eval;
+    let text = r#"o: set(obj(), "compress", false); [get(o, "compress"), has(o, "compress")]"#;
+    assert_eq!(eval(text).as_list().unwrap()[0].as_bool(), Some(false));
+    assert!(matches!(eval(r#"get(obj(), "missing")"#), Value::None));
+    let text = r#"o: { a: 1 }; del(o, "a"); [has(o, "a"), has(del(o, "b"), "b")]"#;
+    assert!(eval(text)
+        .as_list()
+        .unwrap()
+        .iter()
+        .all(|has| has.as_bool() == Some(false)));
 }
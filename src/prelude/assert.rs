@@ -0,0 +1,71 @@
+use std::rc::Rc;
+
+use super::{arguments, equals, expect_bool, register, unexpected_type};
+use crate::{error::Error, execution_tree::parser::ParserBuilder, value::Value};
+
+pub fn assert_prelude(builder: &mut ParserBuilder) {
+    register(
+        builder,
+        &[("assert", 2, assert), ("assert_eq", 2, assert_eq)],
+    );
+}
+
+/// Fails with the message unless the condition is true.
+fn assert(args: Vec<Value>) -> Result<Value, Error> {
+    let [condition, message] = arguments(args)?;
+    if expect_bool(condition, "assert")? {
+        return Ok(Value::None);
+    }
+    match message {
+        Value::String(message) => Err(Error::new_assertion(message, None)),
+        other => Err(unexpected_type("string", &other, "assert")),
+    }
+}
+
+/// Fails unless both values are equal, objects being compared by their fields.
+fn assert_eq(args: Vec<Value>) -> Result<Value, Error> {
+    let [left, right] = arguments(args)?;
+    if same(&left, &right, &mut Vec::new()) {
+        return Ok(Value::None);
+    }
+    Err(Error::new_assertion("values differ", Some((left, right))))
+}
+
+/// Like `eq`, but looking into objects. `compared` holds the pairs of objects being compared
+/// further up, which are assumed equal when they recur.
+fn same(left: &Value, right: &Value, compared: &mut Vec<(usize, usize)>) -> bool {
+    match (left, right) {
+        (Value::List(l), Value::List(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| same(l, r, compared))
+        }
+        (Value::Object(l), Value::Object(r)) if Rc::ptr_eq(l, r) => true,
+        (Value::Object(l), Value::Object(r)) => {
+            let pair = (Rc::as_ptr(l) as usize, Rc::as_ptr(r) as usize);
+            if compared.contains(&pair) {
+                return true;
+            }
+            compared.push(pair);
+            let (l, r) = (l.borrow(), r.borrow());
+            let same = l.len() == r.len()
+                && l.iter()
+                    .all(|(name, l)| r.get(name).is_some_and(|r| same(l, r, compared)));
+            compared.pop();
+            same
+        }
+        (l, r) => equals(l, r),
+    }
+}
+
+#[test]
+fn test_assert_eq() {
+    let object =
+        |value: Value| Value::from(std::collections::HashMap::from([("a".to_string(), value)]));
+    assert!(assert_eq(vec![object(1.into()), object(1.0.into())]).is_ok());
+    let error = assert_eq(vec![object(1.into()), object("1".into())]).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "assertion error: values differ\n  left:  { a: 1 }\n  right: { a: \"1\" }"
+    );
+    let error = assert(vec![false.into(), "no sum".into()]).unwrap_err();
+    assert_eq!(error.to_string(), "assertion error: no sum");
+}
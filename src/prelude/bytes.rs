@@ -0,0 +1,162 @@
+use super::{arguments, expect_bytes, expect_string, register, unexpected_type};
+use crate::{error::Error, execution_tree::parser::ParserBuilder, value::Value};
+
+pub fn bytes_prelude(builder: &mut ParserBuilder) {
+    register(
+        builder,
+        &[
+            ("bytes", 1, bytes),
+            ("utf8", 1, utf8),
+            ("hex", 1, hex),
+            ("from_hex", 1, from_hex),
+            ("base64", 1, base64),
+            ("from_base64", 1, from_base64),
+        ],
+    );
+}
+
+/// The UTF-8 encoding of a string, or the bytes of a list of integers.
+fn bytes(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    match input {
+        Value::Bytes(bytes) => Ok(bytes.into()),
+        Value::String(string) => Ok(string.into_bytes().into()),
+        Value::List(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Int(n) => u8::try_from(n)
+                    .map_err(|_| Error::new_native_call(format!("'bytes' found {n}, not a byte"))),
+                other => Err(unexpected_type("list of integers", &other, "bytes")),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::from),
+        other => Err(unexpected_type("string or list", &other, "bytes")),
+    }
+}
+
+fn utf8(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    let bytes = expect_bytes(input, "utf8")?;
+    String::from_utf8(bytes).map(Value::from).map_err(|error| {
+        let offset = error.utf8_error().valid_up_to();
+        Error::new_native_call(format!("invalid utf-8 at byte {offset}"))
+    })
+}
+
+/// Lowercase hexadecimal encoding.
+fn hex(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    let bytes = expect_bytes(input, "hex")?;
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    Ok(hex.into())
+}
+
+fn from_hex(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    let hex = expect_string(input, "from_hex")?;
+    let invalid = || Error::new_native_call(format!("'from_hex' cannot decode '{hex}'"));
+    let digits = hex
+        .chars()
+        .map(|c| c.to_digit(16).ok_or_else(invalid))
+        .collect::<Result<Vec<_>, _>>()?;
+    if digits.len() % 2 != 0 {
+        return Err(invalid());
+    }
+    let bytes: Vec<u8> = digits
+        .chunks(2)
+        .map(|pair| (pair[0] * 16 + pair[1]) as u8)
+        .collect();
+    Ok(bytes.into())
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 encoding, with padding.
+fn base64(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    let bytes = expect_bytes(input, "base64")?;
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | (*byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (group >> (18 - 6 * index)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    Ok(encoded.into())
+}
+
+/// Decodes standard base64, padding being optional.
+fn from_base64(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    let encoded = expect_string(input, "from_base64")?;
+    let invalid = || Error::new_native_call(format!("'from_base64' cannot decode '{encoded}'"));
+    let sextets = encoded
+        .trim_end_matches('=')
+        .bytes()
+        .map(|c| {
+            BASE64_ALPHABET
+                .iter()
+                .position(|letter| *letter == c)
+                .map(|sextet| sextet as u32)
+                .ok_or_else(invalid)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut bytes = Vec::new();
+    for chunk in sextets.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(invalid());
+        }
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, sextet)| {
+                group | sextet << (18 - 6 * index)
+            });
+        for index in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * index)) as u8);
+        }
+    }
+    Ok(bytes.into())
+}
+
+#[test]
+fn test_base64_round_trip() {
+    for (raw, encoded) in [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foob", "Zm9vYg=="),
+    ] {
+        let value = base64(vec![raw.as_bytes().to_vec().into()]).unwrap();
+        assert_eq!(value.as_string().unwrap(), encoded);
+        let value = from_base64(vec![encoded.into()]).unwrap();
+        assert_eq!(value.as_bytes().unwrap(), raw.as_bytes());
+    }
+    assert!(from_base64(vec!["Z".into()]).is_err());
+}
+
+#[test]
+fn test_bytes_functions() {
+    use crate::engine::{eval, Engine};
+    let text =
+        r#"b: concat(bytes("hé"), bytes([0, 255])); str([len(b), at(b, -1), hex(slice(b, 1, 3))])"#;
+    assert_eq!(eval(text).as_string(), Some("[5, 255, c3a9]"));
+    let text = r#"utf8(from_base64(base64(from_hex(hex(bytes("round trip"))))))"#;
+    assert_eq!(eval(text).as_string(), Some("round trip"));
+    assert!(Engine::new().eval("utf8(bytes([255]))").is_err());
+
+    let path = std::env::temp_dir().join(format!("porte-bytes-{}", std::process::id()));
+    std::fs::write(&path, [0xde, 0xad, 0xbe, 0xef]).unwrap();
+    let text = format!("hex(read_raw({:?}))", path.display().to_string());
+    let value = eval(&text);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(value.as_string(), Some("deadbeef"));
+}
@@ -0,0 +1,88 @@
+use std::{collections::HashMap, env};
+
+use super::{arguments, expect_string, fs::io_error, register, unexpected_type};
+use crate::{error::Error, execution_tree::parser::ParserBuilder, value::Value};
+
+pub fn env_prelude(builder: &mut ParserBuilder) {
+    register(
+        builder,
+        &[
+            ("env_get", 1, env_get),
+            ("env_set", 2, env_set),
+            ("env_vars", 0, env_vars),
+            ("cwd", 0, cwd),
+            ("chdir", 1, chdir),
+        ],
+    );
+}
+
+/// The value of an environment variable, `none` when it is unset or not unicode.
+fn env_get(args: Vec<Value>) -> Result<Value, Error> {
+    let [name] = arguments(args)?;
+    let name = expect_string(name, "env_get")?;
+    Ok(env::var(name).ok().into())
+}
+
+/// Sets an environment variable for the script and the commands it runs, `none` removing it.
+fn env_set(args: Vec<Value>) -> Result<Value, Error> {
+    let [name, value] = arguments(args)?;
+    let name = expect_string(name, "env_set")?;
+    if name.is_empty() || name.contains(['=', '\0']) {
+        let message = format!("'env_set' cannot name a variable '{name}'");
+        return Err(Error::new_native_call(message));
+    }
+    match value {
+        Value::None => env::remove_var(name),
+        Value::String(value) if !value.contains('\0') => env::set_var(name, value),
+        other => return Err(unexpected_type("string or none", &other, "env_set")),
+    }
+    Ok(Value::None)
+}
+
+/// An object holding every unicode environment variable.
+fn env_vars(args: Vec<Value>) -> Result<Value, Error> {
+    let [] = arguments(args)?;
+    let variables: HashMap<_, _> = env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .map(|(name, value)| (name, value.into()))
+        .collect();
+    Ok(variables.into())
+}
+
+fn cwd(args: Vec<Value>) -> Result<Value, Error> {
+    let [] = arguments(args)?;
+    let cwd = env::current_dir().map_err(|error| io_error(error, "."))?;
+    Ok(cwd.display().to_string().into())
+}
+
+/// Changes the current directory, against which relative paths are resolved.
+fn chdir(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "chdir")?;
+    env::set_current_dir(&path).map_err(|error| io_error(error, &path))?;
+    Ok(Value::None)
+}
+
+#[test]
+fn test_env_functions() {
+    // tests run in parallel threads, so these only read the environment of the process
+    let variables = env_vars(vec![]).unwrap();
+    let variables = variables.as_object().unwrap();
+    for (name, value) in env::vars() {
+        assert_eq!(variables[&name].as_string(), Some(value.as_str()));
+        let read = env_get(vec![name.into()]).unwrap();
+        assert_eq!(read.as_string(), Some(value.as_str()));
+    }
+    let unset = env_get(vec![format!("PORTE_UNSET_{}", std::process::id()).into()]);
+    assert!(matches!(unset.unwrap(), Value::None));
+    let directory = cwd(vec![]).unwrap();
+    let expected = env::current_dir().unwrap().display().to_string();
+    assert_eq!(directory.as_string(), Some(expected.as_str()));
+
+    let error = env_set(vec!["A=B".into(), "c".into()]).unwrap_err();
+    assert!(matches!(error, Error::NativeCall(_)));
+    let error = env_set(vec!["PORTE_TEST".into(), 1i64.into()]).unwrap_err();
+    assert!(matches!(error, Error::Type(_)));
+    let error = chdir(vec!["/nonexistent/porte".into()]).unwrap_err();
+    assert!(matches!(error, Error::Io(_)));
+}
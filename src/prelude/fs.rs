@@ -0,0 +1,251 @@
+use std::{collections::HashMap, fs, io, path::Path, time::UNIX_EPOCH};
+
+use super::{arguments, expect_string, register, unexpected_type};
+use crate::{error::Error, execution_tree::parser::ParserBuilder, value::Value};
+
+pub fn fs_prelude(builder: &mut ParserBuilder) {
+    register(
+        builder,
+        &[
+            ("read", 1, read),
+            ("read_raw", 1, read_raw),
+            ("write", 2, write),
+            ("append", 2, append),
+            ("exists", 1, exists),
+            ("is_dir", 1, is_dir),
+            ("mkdir", 1, mkdir),
+            ("mkdir_all", 1, mkdir_all),
+            ("remove", 1, remove),
+            ("remove_all", 1, remove_all),
+            ("copy", 2, copy),
+            ("rename", 2, rename),
+            ("list_dir", 1, list_dir),
+            ("metadata", 1, metadata),
+        ],
+    );
+}
+
+/// Keeps the kind of an io error, prefixing its message with the path involved.
+pub(crate) fn io_error(error: io::Error, path: &str) -> Error {
+    io::Error::new(error.kind(), format!("{path}: {error}")).into()
+}
+
+/// The content of a text file.
+fn read(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "read")?;
+    let text = fs::read_to_string(&path).map_err(|error| io_error(error, &path))?;
+    Ok(text.into())
+}
+
+/// The content of a file as bytes, whatever its encoding.
+fn read_raw(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "read_raw")?;
+    let bytes = fs::read(&path).map_err(|error| io_error(error, &path))?;
+    Ok(bytes.into())
+}
+
+/// The bytes to write for a string or bytes value.
+fn expect_content(value: Value, function: &str) -> Result<Vec<u8>, Error> {
+    match value {
+        Value::String(string) => Ok(string.into_bytes()),
+        Value::Bytes(bytes) => Ok(bytes),
+        other => Err(unexpected_type("string or bytes", &other, function)),
+    }
+}
+
+/// Replaces the content of a file, creating it if needed.
+fn write(args: Vec<Value>) -> Result<Value, Error> {
+    let [path, content] = arguments(args)?;
+    let path = expect_string(path, "write")?;
+    let content = expect_content(content, "write")?;
+    fs::write(&path, content).map_err(|error| io_error(error, &path))?;
+    Ok(Value::None)
+}
+
+/// Adds to the end of a file, creating it if needed.
+fn append(args: Vec<Value>) -> Result<Value, Error> {
+    use std::io::Write;
+
+    let [path, content] = arguments(args)?;
+    let path = expect_string(path, "append")?;
+    let content = expect_content(content, "append")?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(&content))
+        .map_err(|error| io_error(error, &path))?;
+    Ok(Value::None)
+}
+
+fn exists(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "exists")?;
+    let exists = Path::new(&path)
+        .try_exists()
+        .map_err(|error| io_error(error, &path))?;
+    Ok(exists.into())
+}
+
+fn is_dir(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "is_dir")?;
+    Ok(fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_dir())
+        .into())
+}
+
+fn mkdir(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "mkdir")?;
+    fs::create_dir(&path).map_err(|error| io_error(error, &path))?;
+    Ok(Value::None)
+}
+
+/// Creates a directory and its missing parents, succeeding if it already exists.
+fn mkdir_all(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "mkdir_all")?;
+    fs::create_dir_all(&path).map_err(|error| io_error(error, &path))?;
+    Ok(Value::None)
+}
+
+/// Removes a file or an empty directory.
+fn remove(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "remove")?;
+    fs::symlink_metadata(&path)
+        .and_then(|metadata| {
+            if metadata.is_dir() {
+                fs::remove_dir(&path)
+            } else {
+                fs::remove_file(&path)
+            }
+        })
+        .map_err(|error| io_error(error, &path))?;
+    Ok(Value::None)
+}
+
+/// Removes a file, or a directory with all its content.
+fn remove_all(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "remove_all")?;
+    fs::symlink_metadata(&path)
+        .and_then(|metadata| {
+            if metadata.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            }
+        })
+        .map_err(|error| io_error(error, &path))?;
+    Ok(Value::None)
+}
+
+/// Copies a file, returning the number of bytes copied.
+fn copy(args: Vec<Value>) -> Result<Value, Error> {
+    let [from, to] = arguments(args)?;
+    let from = expect_string(from, "copy")?;
+    let to = expect_string(to, "copy")?;
+    let copied =
+        fs::copy(&from, &to).map_err(|error| io_error(error, &format!("{from} -> {to}")))?;
+    Ok((copied as i64).into())
+}
+
+fn rename(args: Vec<Value>) -> Result<Value, Error> {
+    let [from, to] = arguments(args)?;
+    let from = expect_string(from, "rename")?;
+    let to = expect_string(to, "rename")?;
+    fs::rename(&from, &to).map_err(|error| io_error(error, &format!("{from} -> {to}")))?;
+    Ok(Value::None)
+}
+
+/// The names of the entries of a directory, sorted.
+fn list_dir(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "list_dir")?;
+    let mut names = fs::read_dir(&path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .map_err(|error| io_error(error, &path))?;
+    names.sort();
+    let names: Vec<Value> = names.into_iter().map(Value::from).collect();
+    Ok(names.into())
+}
+
+/// Size in bytes, modification time in seconds since the unix epoch, unix permission bits and
+/// kind of a path, following symbolic links.
+fn metadata(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "metadata")?;
+    let metadata = fs::metadata(&path).map_err(|error| io_error(error, &path))?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_secs() as i64);
+    #[cfg(unix)]
+    let permissions = {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() as i64 & 0o7777)
+    };
+    #[cfg(not(unix))]
+    let permissions: Option<i64> = None;
+
+    let fields = HashMap::from([
+        ("size".to_string(), (metadata.len() as i64).into()),
+        ("mtime".to_string(), mtime.into()),
+        ("permissions".to_string(), permissions.into()),
+        (
+            "readonly".to_string(),
+            metadata.permissions().readonly().into(),
+        ),
+        ("is_dir".to_string(), metadata.is_dir().into()),
+        ("is_file".to_string(), metadata.is_file().into()),
+    ]);
+    Ok(fields.into())
+}
+
+#[test]
+fn test_fs_functions() {
+    let root = std::env::temp_dir().join(format!("porte-fs-{}", std::process::id()));
+    let path = |relative: &str| Value::from(root.join(relative).display().to_string());
+    mkdir_all(vec![path("a/b")]).unwrap();
+    write(vec![path("a/f.txt"), "hello".into()]).unwrap();
+    append(vec![path("a/f.txt"), b" world".to_vec().into()]).unwrap();
+    copy(vec![path("a/f.txt"), path("a/b/g.txt")]).unwrap();
+    rename(vec![path("a/b/g.txt"), path("a/h.txt")]).unwrap();
+    remove(vec![path("a/f.txt")]).unwrap();
+    let names = list_dir(vec![path("a")]).unwrap();
+    let text = read(vec![path("a/h.txt")]).unwrap();
+    let metadata = metadata(vec![path("a/h.txt")]).unwrap();
+    let removed = exists(vec![path("a/f.txt")]).unwrap();
+    let directory = is_dir(vec![path("a/b")]).unwrap();
+    remove_all(vec![root.display().to_string().into()]).unwrap();
+
+    assert!(!root.exists());
+    let names: Vec<_> = names
+        .as_list()
+        .unwrap()
+        .iter()
+        .map(Value::as_string)
+        .collect();
+    assert_eq!(names, [Some("b"), Some("h.txt")]);
+    assert_eq!(text.as_string(), Some("hello world"));
+    let metadata = metadata.as_object().unwrap();
+    assert_eq!(metadata["size"].as_int(), Some(11));
+    assert_eq!(metadata["is_file"].as_bool(), Some(true));
+    assert_eq!(removed.as_bool(), Some(false));
+    assert_eq!(directory.as_bool(), Some(true));
+
+    let error = read(vec!["/nonexistent/porte".into()]).unwrap_err();
+    let Error::Io(error) = error else {
+        panic!("expected an io error");
+    };
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
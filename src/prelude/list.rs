@@ -0,0 +1,257 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use super::{arguments, expect_function, expect_list, register, unexpected_type};
+use crate::{
+    error::Error,
+    execution_tree::parser::ParserBuilder,
+    runtime::Caller,
+    value::{function::Function, Value},
+};
+
+pub fn list_prelude(builder: &mut ParserBuilder) {
+    register(
+        builder,
+        &[
+            ("len", 1, len),
+            ("push", 2, push),
+            ("pop", 1, pop),
+            ("at", 2, at),
+            ("slice", 3, slice),
+            ("concat", 2, concat),
+        ],
+    );
+
+    type HigherOrderOper = fn(Vec<Value>, &mut Caller) -> Result<Value, Error>;
+    let functions: Vec<(_, _, HigherOrderOper)> = vec![
+        ("map", 2, map),
+        ("filter", 2, filter),
+        ("fold", 3, fold),
+        ("sort_by", 2, sort_by),
+    ];
+    for (name, arg_count, closure) in functions {
+        let function = Function::new_higher_order(arg_count, closure);
+        builder.prelude(name.into(), function.into());
+    }
+}
+
+fn len(args: Vec<Value>) -> Result<Value, Error> {
+    let [input] = arguments(args)?;
+    let len = match input {
+        Value::List(list) => list.len(),
+        Value::String(string) => string.chars().count(),
+        Value::Bytes(bytes) => bytes.len(),
+        other => return Err(unexpected_type("list, string or bytes", &other, "len")),
+    };
+    Ok((len as i64).into())
+}
+
+fn push(args: Vec<Value>) -> Result<Value, Error> {
+    let [list, item] = arguments(args)?;
+    let mut list = expect_list(list, "push")?;
+    list.push(item);
+    Ok(list.into())
+}
+
+/// Removes the last item, returning an object holding the shortened `list` and that `item`.
+fn pop(args: Vec<Value>) -> Result<Value, Error> {
+    let [list] = arguments(args)?;
+    let mut list = expect_list(list, "pop")?;
+    let item = list
+        .pop()
+        .ok_or_else(|| Error::new_native_call("popping an empty list"))?;
+    let fields = HashMap::from([
+        ("list".to_string(), list.into()),
+        ("item".to_string(), item),
+    ]);
+    Ok(fields.into())
+}
+
+/// Reads an item or a byte, negative indices counting from the end, `none` when out of bounds.
+fn at(args: Vec<Value>) -> Result<Value, Error> {
+    let [sequence, index] = arguments(args)?;
+    let index = expect_index(index, "at")?;
+    let position = |len: usize| {
+        let index = if index < 0 { index + len as i64 } else { index };
+        usize::try_from(index).ok()
+    };
+    let item = match sequence {
+        Value::List(list) => position(list.len()).and_then(|index| list.get(index).cloned()),
+        Value::Bytes(bytes) => position(bytes.len())
+            .and_then(|index| bytes.get(index))
+            .map(|byte| Value::Int(*byte as i64)),
+        other => return Err(unexpected_type("list or bytes", &other, "at")),
+    };
+    Ok(item.into())
+}
+
+/// The items from `start` included to `end` excluded, `none` meaning the end of the list.
+fn slice(args: Vec<Value>) -> Result<Value, Error> {
+    let [sequence, start, end] = arguments(args)?;
+    let len = match &sequence {
+        Value::List(list) => list.len(),
+        Value::Bytes(bytes) => bytes.len(),
+        other => return Err(unexpected_type("list or bytes", other, "slice")),
+    };
+    let bound = |index: i64| {
+        let index = if index < 0 { index + len as i64 } else { index };
+        index.clamp(0, len as i64) as usize
+    };
+    let start = bound(expect_index(start, "slice")?);
+    let end = match end {
+        Value::None => len,
+        end => bound(expect_index(end, "slice")?),
+    };
+    let range = start..end.max(start);
+    match sequence {
+        Value::Bytes(bytes) => Ok(bytes[range].to_vec().into()),
+        sequence => Ok(expect_list(sequence, "slice")?[range].to_vec().into()),
+    }
+}
+
+fn concat(args: Vec<Value>) -> Result<Value, Error> {
+    let [lhs, rhs] = arguments(args)?;
+    match (lhs, rhs) {
+        (Value::List(mut lhs), Value::List(rhs)) => {
+            lhs.extend(rhs);
+            Ok(lhs.into())
+        }
+        (Value::Bytes(mut lhs), Value::Bytes(rhs)) => {
+            lhs.extend(rhs);
+            Ok(lhs.into())
+        }
+        (lhs, rhs) => Err(Error::new_type(format!(
+            "'concat' expected two lists or two bytes, found {} and {}",
+            lhs.type_name(),
+            rhs.type_name()
+        ))),
+    }
+}
+
+fn map(args: Vec<Value>, caller: &mut Caller) -> Result<Value, Error> {
+    let [list, function] = arguments(args)?;
+    let list = expect_list(list, "map")?;
+    let function = expect_function(function, "map")?;
+    let items = list
+        .into_iter()
+        .map(|item| caller.call(&function, vec![item]))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items.into())
+}
+
+fn filter(args: Vec<Value>, caller: &mut Caller) -> Result<Value, Error> {
+    let [list, predicate] = arguments(args)?;
+    let list = expect_list(list, "filter")?;
+    let predicate = expect_function(predicate, "filter")?;
+    let mut items = Vec::new();
+    for item in list {
+        let kept = caller.call(&predicate, vec![item.clone()])?;
+        let kept = kept.as_bool().ok_or_else(|| {
+            Error::new_type(format!(
+                "'filter' expected the predicate to return a bool, found {}",
+                kept.type_name()
+            ))
+        })?;
+        if kept {
+            items.push(item);
+        }
+    }
+    Ok(items.into())
+}
+
+/// Combines the items from the first, `function` receiving the accumulator then the item.
+fn fold(args: Vec<Value>, caller: &mut Caller) -> Result<Value, Error> {
+    let [list, initial, function] = arguments(args)?;
+    let list = expect_list(list, "fold")?;
+    let function = expect_function(function, "fold")?;
+    list.into_iter().try_fold(initial, |accumulator, item| {
+        caller.call(&function, vec![accumulator, item])
+    })
+}
+
+/// Stable sort on the numbers or strings returned by `key` for every item.
+fn sort_by(args: Vec<Value>, caller: &mut Caller) -> Result<Value, Error> {
+    let [list, key] = arguments(args)?;
+    let list = expect_list(list, "sort_by")?;
+    let key = expect_function(key, "sort_by")?;
+    let keys = list
+        .iter()
+        .map(|item| caller.call(&key, vec![item.clone()]))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(pair) = keys
+        .windows(2)
+        .find(|pair| compare(&pair[0], &pair[1]).is_none())
+    {
+        return Err(Error::new_type(format!(
+            "'sort_by' cannot compare keys {} and {}",
+            pair[0].type_name(),
+            pair[1].type_name()
+        )));
+    }
+
+    let mut items: Vec<_> = keys.into_iter().zip(list).collect();
+    items.sort_by(|(lhs, _), (rhs, _)| compare(lhs, rhs).unwrap_or(Ordering::Equal));
+    let items: Vec<_> = items.into_iter().map(|(_, item)| item).collect();
+    Ok(items.into())
+}
+
+fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Value::Int(l), Value::Int(r)) => Some(l.cmp(r)),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        _ => lhs.as_number()?.partial_cmp(&rhs.as_number()?),
+    }
+}
+
+fn expect_index(value: Value, function: &str) -> Result<i64, Error> {
+    match value {
+        Value::Int(n) => Ok(n),
+        Value::Number(n) if n.fract() == 0. => Ok(n as i64),
+        other => Err(unexpected_type("integer", &other, function)),
+    }
+}
+
+#[test]
+fn test_slice_bounds() {
+    let list = Value::List(vec![1.into(), 2.into(), 3.into()]);
+    let slice = |start: f64, end: Value| {
+        let sliced = slice(vec![list.clone(), start.into(), end]).unwrap();
+        sliced.as_list().unwrap().len()
+    };
+    assert_eq!(slice(1., Value::None), 2);
+    assert_eq!(slice(-2., (-1.).into()), 1);
+    assert_eq!(slice(2., 1.0.into()), 0);
+    assert_eq!(slice(0., 10.0.into()), 3);
+}
+
+#[test]
+fn test_pop() {
+    let list = Value::List(vec![1i64.into(), 2i64.into()]);
+    let popped = pop(vec![list]).unwrap();
+    let popped = popped.as_object().unwrap();
+    assert_eq!(popped["item"].as_int(), Some(2));
+    let rest: Vec<_> = popped["list"]
+        .as_list()
+        .unwrap()
+        .iter()
+        .map(Value::as_int)
+        .collect();
+    assert_eq!(rest, [Some(1)]);
+    let error = pop(vec![Value::List(Vec::new())]).unwrap_err();
+    assert!(matches!(error, Error::NativeCall(_)));
+}
+
+#[test]
+fn test_list_functions() {
+    use crate::engine::{eval, Engine};
+    let text = "a: 2; l: [a, a + 1, [], ]; l <- push(l, 4); len(l) * 10 + at(l, -1)";
+    assert_eq!(eval(text).as_number().unwrap(), 44.);
+    let text = "l: map(filter([3, 1, 4, 2], (x) => { x > 1 }), (x) => { x * 10 });
+        fold(sort_by(l, (x) => { -x }), \"\", (acc, x) => { acc + x + \" \" })";
+    assert_eq!(eval(text).as_string().unwrap(), "40 30 20 ");
+    assert!(matches!(eval("at([], 0)"), Value::None));
+    assert!(eval("[1, [2]] == [1, [2]]").as_bool().unwrap());
+    let error = Engine::new()
+        .eval("map([1], (x) => { x + true })")
+        .unwrap_err();
+    assert_eq!(error.trace().unwrap()[1].function_name, "map");
+}
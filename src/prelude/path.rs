@@ -0,0 +1,195 @@
+use std::path::{self, Component, Path, PathBuf};
+
+use super::{arguments, expect_string, fs::io_error, register};
+use crate::{error::Error, execution_tree::parser::ParserBuilder, value::Value};
+
+pub fn path_prelude(builder: &mut ParserBuilder) {
+    register(
+        builder,
+        &[
+            ("path_join", 2, path_join),
+            ("path_parent", 1, path_parent),
+            ("path_file_name", 1, path_file_name),
+            ("path_stem", 1, path_stem),
+            ("path_extension", 1, path_extension),
+            ("path_with_extension", 2, path_with_extension),
+            ("path_normalize", 1, path_normalize),
+            ("path_absolute", 1, path_absolute),
+            ("path_relative_to", 2, path_relative_to),
+        ],
+    );
+}
+
+fn path_value(path: &Path) -> Value {
+    path.display().to_string().into()
+}
+
+/// Appends `other` to `base`, an absolute `other` replacing `base`.
+fn path_join(args: Vec<Value>) -> Result<Value, Error> {
+    let [base, other] = arguments(args)?;
+    let base = expect_string(base, "path_join")?;
+    let other = expect_string(other, "path_join")?;
+    Ok(path_value(&Path::new(&base).join(other)))
+}
+
+/// The path without its last component, `none` for a root or an empty path.
+fn path_parent(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "path_parent")?;
+    Ok(Path::new(&path).parent().map(path_value).into())
+}
+
+fn path_file_name(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "path_file_name")?;
+    let name = Path::new(&path).file_name();
+    Ok(name.map(|name| name.to_string_lossy().into_owned()).into())
+}
+
+/// The file name without its extension.
+fn path_stem(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "path_stem")?;
+    let stem = Path::new(&path).file_stem();
+    Ok(stem.map(|stem| stem.to_string_lossy().into_owned()).into())
+}
+
+/// The extension without its dot, `none` for names without one or starting with the only dot.
+fn path_extension(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "path_extension")?;
+    let extension = Path::new(&path).extension();
+    Ok(extension
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .into())
+}
+
+/// Replaces or adds the extension, an empty one removing it.
+fn path_with_extension(args: Vec<Value>) -> Result<Value, Error> {
+    let [path, extension] = arguments(args)?;
+    let path = expect_string(path, "path_with_extension")?;
+    let extension = expect_string(extension, "path_with_extension")?;
+    Ok(path_value(&Path::new(&path).with_extension(extension)))
+}
+
+/// Removes `.` components and resolves `..` ones without reading the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => (),
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+fn path_normalize(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "path_normalize")?;
+    Ok(path_value(&normalize(Path::new(&path))))
+}
+
+/// The normalized path, relative ones being taken from the current directory.
+fn path_absolute(args: Vec<Value>) -> Result<Value, Error> {
+    let [path] = arguments(args)?;
+    let path = expect_string(path, "path_absolute")?;
+    let absolute = path::absolute(&path).map_err(|error| io_error(error, &path))?;
+    Ok(path_value(&normalize(&absolute)))
+}
+
+/// The path leading from `base` to `path`, both being either absolute or relative.
+fn path_relative_to(args: Vec<Value>) -> Result<Value, Error> {
+    let [path, base] = arguments(args)?;
+    let path = expect_string(path, "path_relative_to")?;
+    let base = expect_string(base, "path_relative_to")?;
+    let unrelated = || {
+        Error::new_native_call(format!(
+            "'path_relative_to' cannot lead from '{base}' to '{path}'"
+        ))
+    };
+    let normalized_path = normalize(Path::new(&path));
+    let normalized_base = normalize(Path::new(&base));
+    if normalized_path.is_absolute() != normalized_base.is_absolute() {
+        return Err(unrelated());
+    }
+
+    let mut path_components = normalized_path.components().peekable();
+    let mut base_components = normalized_base.components().peekable();
+    while path_components.peek().is_some() && path_components.peek() == base_components.peek() {
+        path_components.next();
+        base_components.next();
+    }
+    let mut relative = PathBuf::new();
+    for component in base_components {
+        match component {
+            Component::Normal(_) => relative.push(".."),
+            Component::CurDir => (),
+            _ => return Err(unrelated()),
+        }
+    }
+    relative.extend(path_components.filter(|component| *component != Component::CurDir));
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    Ok(path_value(&relative))
+}
+
+#[test]
+fn test_normalize() {
+    for (path, normalized) in [
+        ("a/./b/../c/", "a/c"),
+        ("../a/..", ".."),
+        ("a/../..", ".."),
+        ("/../a", "/a"),
+        ("./", "."),
+        ("", "."),
+    ] {
+        assert_eq!(normalize(Path::new(path)), Path::new(normalized), "{path}");
+    }
+}
+
+#[test]
+fn test_path_functions() {
+    use super::FunctOper;
+    let call = |function: FunctOper, args: &[&str]| {
+        let args = args.iter().map(|arg| Value::from(*arg)).collect();
+        function(args).unwrap().as_string().map(str::to_string)
+    };
+    let some = |path: &str| Some(path.to_string());
+    assert_eq!(call(path_join, &["a/", "b.txt"]), some("a/b.txt"));
+    assert_eq!(call(path_join, &["a", "/b"]), some("/b"));
+    assert_eq!(call(path_parent, &["a/b.txt"]), some("a"));
+    assert_eq!(call(path_parent, &["/"]), None);
+    assert_eq!(call(path_file_name, &["a/b.tar.gz"]), some("b.tar.gz"));
+    assert_eq!(call(path_stem, &["a/b.tar.gz"]), some("b.tar"));
+    assert_eq!(call(path_extension, &["a/b.tar.gz"]), some("gz"));
+    assert_eq!(call(path_extension, &[".bashrc"]), None);
+    assert_eq!(
+        call(path_with_extension, &["a/b.txt", "md"]),
+        some("a/b.md")
+    );
+    assert_eq!(call(path_normalize, &["./a//b/../c"]), some("a/c"));
+    assert_eq!(call(path_relative_to, &["/a/b/c", "/a/d"]), some("../b/c"));
+    assert_eq!(call(path_relative_to, &["a", "a/b/.."]), some("."));
+    let expected = std::env::current_dir().unwrap().join("b");
+    assert_eq!(
+        call(path_absolute, &["a/../b"]),
+        expected.to_str().map(str::to_string)
+    );
+
+    for (path, base) in [("a", "/a"), ("a", "../b")] {
+        let error = path_relative_to(vec![path.into(), base.into()]).unwrap_err();
+        assert!(matches!(error, Error::NativeCall(_)));
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{
+    arguments, expect_bool, expect_list, expect_object, expect_string, fs::io_error, register,
+    unexpected_type,
+};
+use crate::{error::Error, execution_tree::parser::ParserBuilder, value::Value};
+
+pub fn process_prelude(builder: &mut ParserBuilder) {
+    register(
+        builder,
+        &[("run", 3, run), ("run_streaming", 3, run_streaming)],
+    );
+}
+
+#[derive(Default)]
+struct RunOptions {
+    cwd: Option<String>,
+    env: Vec<(String, Option<String>)>,
+    stdin: Option<Vec<u8>>,
+    timeout: Option<Duration>,
+    check: bool,
+}
+
+impl RunOptions {
+    fn from_value(options: Value, function: &str) -> Result<Self, Error> {
+        let mut run_options = Self::default();
+        if let Value::None = options {
+            return Ok(run_options);
+        }
+        let options = expect_object(options, function)?;
+        for (name, value) in options.borrow().iter() {
+            let value = value.clone();
+            match name.as_str() {
+                "cwd" => run_options.cwd = Some(expect_string(value, function)?),
+                "env" => {
+                    let variables = expect_object(value, function)?;
+                    for (name, value) in variables.borrow().iter() {
+                        let value = match value.clone() {
+                            Value::None => None,
+                            value => Some(expect_string(value, function)?),
+                        };
+                        run_options.env.push((name.clone(), value));
+                    }
+                }
+                "stdin" => {
+                    run_options.stdin = match value {
+                        Value::String(string) => Some(string.into_bytes()),
+                        Value::Bytes(bytes) => Some(bytes),
+                        other => return Err(unexpected_type("string or bytes", &other, function)),
+                    }
+                }
+                "timeout" => {
+                    let seconds = value
+                        .as_number()
+                        .filter(|seconds| seconds.is_finite() && *seconds >= 0.)
+                        .ok_or_else(|| unexpected_type("positive number", &value, function))?;
+                    run_options.timeout = Some(Duration::from_secs_f64(seconds));
+                }
+                "check" => run_options.check = expect_bool(value, function)?,
+                other => {
+                    return Err(Error::new_native_call(format!(
+                        "'{function}' has no option '{other}'"
+                    )))
+                }
+            }
+        }
+        Ok(run_options)
+    }
+}
+
+/// How long outputs are still read after a command timed out, since processes it started may
+/// keep them open.
+const TIMEOUT_GRACE: Duration = Duration::from_millis(100);
+
+/// Reads a child output to the end on its own thread into `collected`, forwarding it to
+/// `forward` if given.
+fn collect<R, W>(
+    mut output: R,
+    mut forward: Option<W>,
+    collected: Arc<Mutex<Vec<u8>>>,
+) -> JoinHandle<io::Result<()>>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        loop {
+            let read = output.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            if let Some(forward) = &mut forward {
+                forward.write_all(&buffer[..read])?;
+                forward.flush()?;
+            }
+            collected.lock().unwrap().extend_from_slice(&buffer[..read]);
+        }
+    })
+}
+
+/// What a reader collected, waiting for it to reach the end of output unless `deadline` passes.
+fn collected(
+    reader: JoinHandle<io::Result<()>>,
+    collected: Arc<Mutex<Vec<u8>>>,
+    deadline: Option<Instant>,
+) -> io::Result<String> {
+    if let Some(deadline) = deadline {
+        while !reader.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    // a reader still blocked past the deadline is left behind, keeping what it read so far
+    if deadline.is_none() || reader.is_finished() {
+        reader.join().expect("output threads do not panic")?;
+    }
+    let collected = collected.lock().unwrap();
+    Ok(String::from_utf8_lossy(&collected).into_owned())
+}
+
+/// Waits for the child to exit, killing it and the processes it started once the timeout
+/// elapses.
+fn wait(child: &mut Child, timeout: Option<Duration>) -> io::Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            kill(child)?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Kills the process group the child leads, or the child alone when it has none.
+#[cfg(unix)]
+fn kill(child: &mut Child) -> io::Result<()> {
+    let group = -(child.id() as libc::pid_t);
+    // SAFETY: `kill` only sends a signal, to the group of the child if it leads one
+    if unsafe { libc::kill(group, libc::SIGKILL) } == 0 {
+        return Ok(());
+    }
+    child.kill()
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) -> io::Result<()> {
+    child.kill()
+}
+
+/// Runs a command to completion, `forward` echoing its outputs as they are produced.
+fn execute(args: Vec<Value>, function: &str, forward: bool) -> Result<Value, Error> {
+    let [command, command_args, options] = arguments(args)?;
+    let command = expect_string(command, function)?;
+    let command_args = expect_list(command_args, function)?
+        .into_iter()
+        .map(|arg| expect_string(arg, function))
+        .collect::<Result<Vec<_>, _>>()?;
+    let options = RunOptions::from_value(options, function)?;
+
+    let mut process = Command::new(&command);
+    // streamed commands may be interactive, unless they are given their input
+    let interactive = forward && options.stdin.is_none();
+    let stdin = if interactive {
+        Stdio::inherit()
+    } else {
+        Stdio::piped()
+    };
+    process
+        .args(&command_args)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // its own group, for a timeout to kill the processes it starts as well, though interactive
+    // commands must stay in the group of the terminal to read from it
+    #[cfg(unix)]
+    if options.timeout.is_some() && !interactive {
+        std::os::unix::process::CommandExt::process_group(&mut process, 0);
+    }
+    if let Some(cwd) = &options.cwd {
+        process.current_dir(cwd);
+    }
+    for (name, value) in &options.env {
+        match value {
+            Some(value) => process.env(name, value),
+            None => process.env_remove(name),
+        };
+    }
+    let io_error = |error| io_error(error, &command);
+    let mut child = process.spawn().map_err(io_error)?;
+
+    if let Some(stdin) = child.stdin.take() {
+        let input = options.stdin.unwrap_or_default();
+        // a child exiting without reading its input closes the pipe, which is not an error
+        thread::spawn(move || {
+            let mut stdin = stdin;
+            let _ = stdin.write_all(&input);
+        });
+    }
+    let stdout_buffer = Arc::new(Mutex::new(Vec::new()));
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stdout = collect(stdout, forward.then(io::stdout), stdout_buffer.clone());
+    let stderr_buffer = Arc::new(Mutex::new(Vec::new()));
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stderr = collect(stderr, forward.then(io::stderr), stderr_buffer.clone());
+
+    let status = wait(&mut child, options.timeout).map_err(io_error)?;
+    let deadline = status.is_none().then(|| Instant::now() + TIMEOUT_GRACE);
+    let stdout = collected(stdout, stdout_buffer, deadline).map_err(io_error)?;
+    let stderr = collected(stderr, stderr_buffer, deadline).map_err(io_error)?;
+
+    let code = status.and_then(|status| status.code());
+    if options.check && code != Some(0) {
+        let reason = match (status, code) {
+            (None, _) => "timed out".to_string(),
+            (_, Some(code)) => format!("exited with status {code}"),
+            (_, None) => "was killed by a signal".to_string(),
+        };
+        let mut message = format!("'{function}' command '{command}' {reason}");
+        if !stderr.trim().is_empty() {
+            message += &format!(": {}", stderr.trim());
+        }
+        return Err(Error::new_native_call(message));
+    }
+
+    let fields = HashMap::from([
+        ("status".to_string(), code.map(i64::from).into()),
+        ("stdout".to_string(), stdout.into()),
+        ("stderr".to_string(), stderr.into()),
+        ("timed_out".to_string(), status.is_none().into()),
+    ]);
+    Ok(fields.into())
+}
+
+/// Runs a command, returning its exit status and captured outputs.
+///
+/// Options: `cwd`, `env` variables to set or, when `none`, remove, `stdin` input, a `timeout` in
+/// seconds and `check` to raise an error unless the command succeeds.
+fn run(args: Vec<Value>) -> Result<Value, Error> {
+    execute(args, "run", false)
+}
+
+/// Like `run`, also forwarding the command outputs as they are produced. The command reads the
+/// input of the script unless given a `stdin` option.
+fn run_streaming(args: Vec<Value>) -> Result<Value, Error> {
+    execute(args, "run_streaming", true)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run() {
+    let strings = |strings: &[&str]| {
+        let strings: Vec<Value> = strings.iter().map(|string| (*string).into()).collect();
+        Value::from(strings)
+    };
+    let object = |fields: Vec<(&str, Value)>| {
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value));
+        Value::from(fields.collect::<HashMap<_, _>>())
+    };
+    let options = object(vec![
+        ("stdin", "piped".into()),
+        ("env", object(vec![("GREETING", "hello".into())])),
+        ("cwd", "/".into()),
+    ]);
+    let script = r#"cat; echo "$GREETING" >&2; pwd >&2; exit 3"#;
+    let echo = run(vec!["sh".into(), strings(&["-c", script]), options]).unwrap();
+    let echo = echo.as_object().unwrap();
+    assert_eq!(echo["status"].as_int(), Some(3));
+    assert_eq!(echo["stdout"].as_string(), Some("piped"));
+    assert_eq!(echo["stderr"].as_string(), Some("hello\n/\n"));
+    assert_eq!(echo["timed_out"].as_bool(), Some(false));
+    let streamed = run_streaming(vec!["echo".into(), strings(&["streamed"]), Value::None]);
+    let streamed = streamed.unwrap().as_object().unwrap()["stdout"].clone();
+    assert_eq!(streamed.as_string(), Some("streamed\n"));
+
+    let checked = object(vec![("check", true.into())]);
+    let failing = strings(&["-c", "echo failed >&2; exit 1"]);
+    let error = run(vec!["sh".into(), failing, checked]).unwrap_err();
+    let Error::NativeCall(message) = error else {
+        panic!("expected a native call error");
+    };
+    assert_eq!(message, "'run' command 'sh' exited with status 1: failed");
+    let error = run(vec!["/nonexistent/porte".into(), strings(&[]), Value::None]).unwrap_err();
+    assert!(matches!(error, Error::Io(_)));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_timeout_kills_started_processes() {
+    let run_shell = |script: &str| {
+        let options = HashMap::from([("timeout".to_string(), Value::from(0.2))]);
+        let args: Vec<Value> = vec!["-c".into(), script.into()];
+        let start = Instant::now();
+        let result = run(vec!["sh".into(), args.into(), options.into()]).unwrap();
+        (result, start.elapsed())
+    };
+    for script in ["sleep 3; true", "echo started; sleep 3 & wait"] {
+        let (result, elapsed) = run_shell(script);
+        assert!(
+            elapsed < Duration::from_secs(2),
+            "'{script}' took {elapsed:?}"
+        );
+        let result = result.as_object().unwrap();
+        assert_eq!(result["timed_out"].as_bool(), Some(true));
+        assert!(matches!(result["status"], Value::None));
+    }
+    let (result, _) = run_shell("echo started; sleep 3 & wait");
+    let stdout = result.as_object().unwrap()["stdout"].clone();
+    assert_eq!(stdout.as_string(), Some("started\n"));
+}
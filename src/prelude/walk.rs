@@ -0,0 +1,470 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    arguments, expect_bool, expect_list, expect_object, expect_string, fs::io_error, register,
+    unexpected_type,
+};
+use crate::{error::Error, execution_tree::parser::ParserBuilder, value::Value};
+
+pub fn walk_prelude(builder: &mut ParserBuilder) {
+    register(builder, &[("glob", 1, glob), ("walk", 2, walk)]);
+}
+
+/// A glob pattern matched against `/` separated paths.
+///
+/// `*` and `?` match within a path segment, `**` matches any number of segments, `[a-z]` and
+/// `[!a-z]` match character classes and `{a,b}` matches either alternative.
+struct Pattern {
+    alternatives: Vec<Vec<Vec<char>>>,
+}
+
+impl Pattern {
+    fn new(pattern: &str) -> Self {
+        let alternatives = expand_braces(pattern)
+            .iter()
+            .map(|alternative| {
+                alternative
+                    .split('/')
+                    .map(|segment| segment.chars().collect())
+                    .collect()
+            })
+            .collect();
+        Self { alternatives }
+    }
+
+    /// Whether the pattern names a segment starting with a dot, which wildcards never match.
+    fn names_hidden(&self) -> bool {
+        let mut segments = self.alternatives.iter().flatten();
+        segments.any(|segment| segment.first() == Some(&'.'))
+    }
+
+    /// Wildcards only match segments starting with a dot when `hidden` is set.
+    fn matches(&self, path: &str, hidden: bool) -> bool {
+        let path: Vec<Vec<char>> = path
+            .split('/')
+            .map(|segment| segment.chars().collect())
+            .collect();
+        self.alternatives
+            .iter()
+            .any(|segments| match_segments(segments, &path, hidden))
+    }
+}
+
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let mut depth = 0;
+    let mut separators = Vec::new();
+    for (index, c) in pattern[open..].char_indices() {
+        let index = open + index;
+        match c {
+            '{' => depth += 1,
+            ',' if depth == 1 => separators.push(index),
+            '}' if depth == 1 => {
+                let (prefix, suffix) = (&pattern[..open], &pattern[index + 1..]);
+                let bounds: Vec<_> = [open]
+                    .into_iter()
+                    .chain(separators)
+                    .chain([index])
+                    .collect();
+                return bounds
+                    .windows(2)
+                    .flat_map(|bounds| {
+                        let alternative = &pattern[bounds[0] + 1..bounds[1]];
+                        expand_braces(&format!("{prefix}{alternative}{suffix}"))
+                    })
+                    .collect();
+            }
+            '}' => depth -= 1,
+            _ => (),
+        }
+    }
+    // an unclosed brace is literal
+    vec![pattern.to_string()]
+}
+
+fn match_segments(pattern: &[Vec<char>], path: &[Vec<char>], hidden: bool) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((segment, rest)) if segment.as_slice() == ['*', '*'] => {
+            match_segments(rest, path, hidden)
+                || path.split_first().is_some_and(|(first, path)| {
+                    (hidden || first.first() != Some(&'.')) && match_segments(pattern, path, hidden)
+                })
+        }
+        Some((segment, rest)) => path.split_first().is_some_and(|(first, path)| {
+            let visible = hidden || first.first() != Some(&'.') || segment.first() == Some(&'.');
+            visible && match_segment(segment, first) && match_segments(rest, path, hidden)
+        }),
+    }
+}
+
+fn match_segment(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|start| match_segment(rest, &name[start..])),
+        Some(('?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some(('[', rest)) => match character_class(rest) {
+            Some((matches, rest)) => name
+                .split_first()
+                .is_some_and(|(first, name)| matches(*first) && match_segment(rest, name)),
+            None => name.first() == Some(&'[') && match_segment(rest, &name[1..]),
+        },
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+/// Parses the class following a `[`, returning its matcher and the rest of the pattern, or `None`
+/// if it is not closed.
+fn character_class(pattern: &[char]) -> Option<(impl Fn(char) -> bool, &[char])> {
+    let (negated, pattern) = match pattern.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+    // a `]` right after the opening bracket is part of the class
+    let end = pattern
+        .iter()
+        .skip(1)
+        .position(|c| *c == ']')
+        .map(|end| end + 1)?;
+    let (class, rest) = (&pattern[..end], &pattern[end + 1..]);
+    let mut ranges = Vec::new();
+    let mut index = 0;
+    while index < class.len() {
+        if index + 2 < class.len() && class[index + 1] == '-' {
+            ranges.push((class[index], class[index + 2]));
+            index += 3;
+        } else {
+            ranges.push((class[index], class[index]));
+            index += 1;
+        }
+    }
+    let matches =
+        move |c: char| ranges.iter().any(|(from, to)| (*from..=*to).contains(&c)) != negated;
+    Some((matches, rest))
+}
+
+#[derive(Default)]
+struct WalkOptions {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    follow_symlinks: bool,
+    max_depth: Option<usize>,
+    hidden: bool,
+    dirs: bool,
+}
+
+impl WalkOptions {
+    /// Patterns without a `/` are matched against the file name, others against the whole
+    /// relative path.
+    fn matches(patterns: &[Pattern], relative: &str) -> bool {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        patterns.iter().any(|pattern| {
+            let single_segment = pattern.alternatives.iter().all(|a| a.len() == 1);
+            let target = if single_segment { name } else { relative };
+            pattern.matches(target, true)
+        })
+    }
+
+    fn from_value(options: Value) -> Result<Self, Error> {
+        let mut walk_options = Self::default();
+        if let Value::None = options {
+            return Ok(walk_options);
+        }
+        let options = expect_object(options, "walk")?;
+        for (name, value) in options.borrow().iter() {
+            let value = value.clone();
+            match name.as_str() {
+                "include" => walk_options.include = patterns(value)?,
+                "exclude" => walk_options.exclude = patterns(value)?,
+                "follow_symlinks" => walk_options.follow_symlinks = expect_bool(value, "walk")?,
+                "max_depth" => {
+                    walk_options.max_depth = match value {
+                        Value::None => None,
+                        Value::Int(depth) if depth >= 0 => Some(depth as usize),
+                        other => return Err(unexpected_type("positive int", &other, "walk")),
+                    }
+                }
+                "hidden" => walk_options.hidden = expect_bool(value, "walk")?,
+                "dirs" => walk_options.dirs = expect_bool(value, "walk")?,
+                other => {
+                    return Err(Error::new_native_call(format!(
+                        "'walk' has no option '{other}'"
+                    )))
+                }
+            }
+        }
+        Ok(walk_options)
+    }
+}
+
+fn patterns(value: Value) -> Result<Vec<Pattern>, Error> {
+    expect_list(value, "walk")?
+        .into_iter()
+        .map(|pattern| Ok(Pattern::new(&expect_string(pattern, "walk")?)))
+        .collect()
+}
+
+/// A path found while walking, relative to the walked directory.
+struct Entry {
+    relative: String,
+    is_dir: bool,
+}
+
+/// Collects the entries under `dir`, pruning excluded and hidden directories.
+///
+/// Only failing to read `dir` itself is an error, entries below it that cannot be read are
+/// skipped.
+fn visit(
+    dir: &Path,
+    relative: &str,
+    depth: usize,
+    options: &WalkOptions,
+    visited: &mut HashSet<PathBuf>,
+    entries: &mut Vec<Entry>,
+) -> Result<(), Error> {
+    if options
+        .max_depth
+        .is_some_and(|max_depth| depth >= max_depth)
+    {
+        return Ok(());
+    }
+    if options.follow_symlinks {
+        // symbolic links may lead back to a directory being walked
+        let canonical = dir
+            .canonicalize()
+            .map_err(|error| io_error(error, &dir.display().to_string()))?;
+        if !visited.insert(canonical) {
+            return Ok(());
+        }
+    }
+    let read_dir = fs::read_dir(dir).map_err(|error| io_error(error, &dir.display().to_string()));
+    for entry in read_dir?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !options.hidden && name.starts_with('.') {
+            continue;
+        }
+        let relative = match relative {
+            "" => name,
+            relative => format!("{relative}/{name}"),
+        };
+        if WalkOptions::matches(&options.exclude, &relative) {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let is_dir = if file_type.is_symlink() && options.follow_symlinks {
+            path.is_dir()
+        } else {
+            file_type.is_dir()
+        };
+        if is_dir {
+            // an unreadable directory is still listed, without its content
+            let _ = visit(&path, &relative, depth + 1, options, visited, entries);
+        }
+        entries.push(Entry { relative, is_dir });
+    }
+    Ok(())
+}
+
+/// The paths under `dir` selected by the options, sorted.
+///
+/// Options: `include` and `exclude` pattern lists, `follow_symlinks`, `max_depth`, `hidden` to
+/// include dot files and `dirs` to include directories along with files.
+fn walk(args: Vec<Value>) -> Result<Value, Error> {
+    let [dir, options] = arguments(args)?;
+    let dir = expect_string(dir, "walk")?;
+    let options = WalkOptions::from_value(options)?;
+    let mut entries = Vec::new();
+    visit(
+        Path::new(&dir),
+        "",
+        0,
+        &options,
+        &mut HashSet::new(),
+        &mut entries,
+    )?;
+    let mut paths: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| options.dirs || !entry.is_dir)
+        .filter(|entry| {
+            options.include.is_empty() || WalkOptions::matches(&options.include, &entry.relative)
+        })
+        .map(|entry| Path::new(&dir).join(entry.relative).display().to_string())
+        .collect();
+    paths.sort();
+    let paths: Vec<Value> = paths.into_iter().map(Value::from).collect();
+    Ok(paths.into())
+}
+
+/// The existing paths matching a pattern, sorted, wildcards skipping dot files.
+fn glob(args: Vec<Value>) -> Result<Value, Error> {
+    let [pattern] = arguments(args)?;
+    let pattern = expect_string(pattern, "glob")?;
+    let segments: Vec<_> = pattern.split('/').collect();
+    let literal = segments
+        .iter()
+        .take_while(|segment| !segment.contains(['*', '?', '[', '{']))
+        .count()
+        .min(segments.len() - 1);
+    let (base, prefix) = match segments[..literal].join("/") {
+        base if literal == 0 => (".".to_string(), base),
+        base if base.is_empty() => ("/".to_string(), "/".to_string()),
+        base => (base.clone(), format!("{base}/")),
+    };
+    let rest = segments[literal..].join("/");
+    let max_depth = (!rest.contains("**")).then_some(segments.len() - literal);
+    let rest = Pattern::new(&rest);
+
+    // hidden directories cannot match unless named, there is no need to walk them
+    let options = WalkOptions {
+        max_depth,
+        hidden: rest.names_hidden(),
+        dirs: true,
+        ..WalkOptions::default()
+    };
+    let mut entries = Vec::new();
+    if Path::new(&base).is_dir() {
+        let base = Path::new(&base);
+        visit(base, "", 0, &options, &mut HashSet::new(), &mut entries)?;
+    }
+    let mut paths: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| rest.matches(&entry.relative, false))
+        .map(|entry| format!("{prefix}{}", entry.relative))
+        .collect();
+    paths.sort();
+    let paths: Vec<Value> = paths.into_iter().map(Value::from).collect();
+    Ok(paths.into())
+}
+
+#[test]
+fn test_pattern() {
+    let matches = |pattern: &str, path: &str| Pattern::new(pattern).matches(path, false);
+    assert!(matches("src/*.rs", "src/main.rs"));
+    assert!(!matches("src/*.rs", "src/prelude/fs.rs"));
+    assert!(matches("src/**/*.rs", "src/main.rs"));
+    assert!(matches("src/**/*.rs", "src/prelude/fs.rs"));
+    assert!(matches("*.{rs,toml}", "Cargo.toml"));
+    assert!(matches("file[0-9][!a]", "file1b"));
+    assert!(!matches("file[0-9][!a]", "file1a"));
+    assert!(matches("?[]]", "a]"));
+    assert!(!matches("*", ".hidden"));
+    assert!(matches(".*", ".hidden"));
+    assert!(!matches("**/*.rs", ".git/a.rs"));
+    assert!(!Pattern::new("**/*.rs").names_hidden());
+    assert!(Pattern::new("{src,.git}/*").names_hidden());
+}
+
+#[test]
+fn test_walk() {
+    let root = std::env::temp_dir().join(format!("porte-walk-{}", std::process::id()));
+    for dir in ["src/lib", ".git", "target"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in [
+        "main.rs",
+        "src/a.rs",
+        "src/lib/b.rs",
+        "src/c.txt",
+        ".git/d.rs",
+        "target/e.rs",
+        ".f.rs",
+    ] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    let prefix = format!("{}/", root.display());
+    let relative = |paths: Result<Value, Error>| -> Vec<String> {
+        let paths = paths.unwrap();
+        let paths = paths.as_list().unwrap().iter();
+        paths
+            .map(|path| {
+                path.as_string()
+                    .unwrap()
+                    .strip_prefix(&prefix)
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    };
+    let pattern = |pattern: &str| Value::from(format!("{prefix}{pattern}"));
+    let walk_root = |options: Value| walk(vec![root.display().to_string().into(), options]);
+    let patterns = |patterns: &[&str]| {
+        let patterns: Vec<Value> = patterns.iter().map(|pattern| (*pattern).into()).collect();
+        Value::from(patterns)
+    };
+    let options = |fields: Vec<(&str, Value)>| {
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value));
+        Value::from(fields.collect::<std::collections::HashMap<_, _>>())
+    };
+
+    let globbed = relative(glob(vec![pattern("src/*.rs")]));
+    let globbed_all = relative(glob(vec![pattern("**/*.{rs,txt}")]));
+    let walked = relative(walk_root(Value::None));
+    let included = relative(walk_root(options(vec![
+        ("include", patterns(&["*.rs"])),
+        ("exclude", patterns(&["target"])),
+        ("max_depth", 2.into()),
+    ])));
+    let with_dirs = relative(walk_root(options(vec![
+        ("include", patterns(&["src/**"])),
+        ("hidden", true.into()),
+        ("dirs", true.into()),
+    ])));
+    let hidden = relative(walk_root(options(vec![
+        ("hidden", true.into()),
+        ("exclude", patterns(&["src", ".git"])),
+    ])));
+    let error = walk_root(options(vec![("depth", 1.into())])).unwrap_err();
+    let hidden_glob = relative(glob(vec![pattern(".*/*.rs")]));
+    fs::remove_dir_all(&root).unwrap();
+
+    let all = [
+        "main.rs",
+        "src/a.rs",
+        "src/c.txt",
+        "src/lib/b.rs",
+        "target/e.rs",
+    ];
+    assert_eq!(globbed, ["src/a.rs"]);
+    assert_eq!(globbed_all, all);
+    assert_eq!(walked, all);
+    assert_eq!(included, ["main.rs", "src/a.rs"]);
+    let expected = ["src", "src/a.rs", "src/c.txt", "src/lib", "src/lib/b.rs"];
+    assert_eq!(with_dirs, expected);
+    assert_eq!(hidden, [".f.rs", "main.rs", "target/e.rs"]);
+    assert!(matches!(error, Error::NativeCall(_)));
+    assert_eq!(hidden_glob, [".git/d.rs"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_walk_unreadable_directory() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = std::env::temp_dir().join(format!("porte-walk-locked-{}", std::process::id()));
+    fs::create_dir_all(root.join("locked")).unwrap();
+    fs::write(root.join("locked/secret.rs"), "").unwrap();
+    fs::write(root.join("open.rs"), "").unwrap();
+    fs::set_permissions(root.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+    // privileged users read the directory anyway
+    let readable = fs::read_dir(root.join("locked")).is_ok();
+    let walked = walk(vec![root.display().to_string().into(), Value::None]);
+    let globbed = glob(vec![format!("{}/**/*.rs", root.display()).into()]);
+    fs::set_permissions(root.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+
+    let count = |paths: Result<Value, Error>| paths.unwrap().as_list().unwrap().len();
+    let expected = if readable { 2 } else { 1 };
+    assert_eq!(count(walked), expected);
+    assert_eq!(count(globbed), expected);
+}
@@ -0,0 +1,236 @@
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::{
+    engine::{Engine, Session, RUNTIME_ERROR_STATUS},
+    error::Error,
+    runtime::Outcome,
+    source::Source,
+    value::Value,
+};
+
+const HELP: &str = "\
+:load <path>  evaluate a script in the session
+:reset        forget every definition
+:env          list the defined variables
+:help         show this message
+:quit         leave, like end of input";
+
+/// Reads inputs from the terminal and prints what they evaluate to, until the end of input or
+/// an `exit` call, returning the status the process should exit with.
+pub fn run(engine: &Engine) -> i32 {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            eprintln!("[error]: cannot read the terminal: {error}");
+            return RUNTIME_ERROR_STATUS;
+        }
+    };
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(".porte_history"));
+    if let Some(history) = &history {
+        // there is no history before the first session
+        let _ = editor.load_history(history);
+    }
+
+    let mut repl = Repl::new(engine);
+    let mut output = io::stdout();
+    let status = loop {
+        let prompt = if repl.buffer.is_empty() {
+            "porte> "
+        } else {
+            "  ...> "
+        };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                if let Some(status) = repl.line(&line, &mut output) {
+                    break status;
+                }
+                if repl.buffer.is_empty() {
+                    let _ = editor.add_history_entry(line.trim());
+                }
+            }
+            // interrupting abandons the pending input, not the session
+            Err(ReadlineError::Interrupted) => repl.buffer.clear(),
+            Err(ReadlineError::Eof) => break 0,
+            Err(error) => {
+                eprintln!("[error]: cannot read the terminal: {error}");
+                break RUNTIME_ERROR_STATUS;
+            }
+        }
+    };
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    status
+}
+
+/// The state of a read-eval-print loop, independent from the terminal.
+pub struct Repl<'a> {
+    engine: &'a Engine,
+    session: Session<'a>,
+    /// Lines of an input whose brackets are not balanced yet.
+    buffer: String,
+}
+
+impl<'a> Repl<'a> {
+    pub fn new(engine: &'a Engine) -> Self {
+        let session = engine.session();
+        let buffer = String::new();
+        Self {
+            engine,
+            session,
+            buffer,
+        }
+    }
+
+    /// Handles a line, returning the exit status once the loop should stop.
+    pub fn line<W: Write>(&mut self, line: &str, output: &mut W) -> Option<i32> {
+        if self.buffer.is_empty() {
+            if let Some(command) = line.trim().strip_prefix(':') {
+                return self.command(command, output);
+            }
+        }
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        if is_incomplete(&self.buffer) {
+            return None;
+        }
+        let input = std::mem::take(&mut self.buffer);
+        if input.trim().is_empty() {
+            return None;
+        }
+        let result = self.session.eval(&input);
+        self.print(result, output)
+    }
+
+    fn command<W: Write>(&mut self, command: &str, output: &mut W) -> Option<i32> {
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+        let argument = argument.trim();
+        match name {
+            "load" if !argument.is_empty() => match fs::read_to_string(argument) {
+                Ok(text) => {
+                    let result = self.session.eval_source(Source::new(argument, text));
+                    return self.print(result, output);
+                }
+                Err(error) => {
+                    let _ = writeln!(output, "[error]: {argument}: {error}");
+                }
+            },
+            "reset" => self.session = self.engine.session(),
+            "env" => {
+                for (name, value) in self.session.variables() {
+                    let _ = writeln!(output, "{name}: {}", value.repr());
+                }
+            }
+            "help" => {
+                let _ = writeln!(output, "{HELP}");
+            }
+            "quit" | "q" => return Some(0),
+            _ => {
+                let _ = writeln!(output, "[error]: unknown command ':{command}'\n{HELP}");
+            }
+        }
+        None
+    }
+
+    fn print<W: Write>(&self, result: Result<Outcome, Error>, output: &mut W) -> Option<i32> {
+        match result {
+            Ok(Outcome::Value(Value::None)) => (),
+            Ok(Outcome::Value(value)) => {
+                let _ = writeln!(output, "{}", value.repr());
+            }
+            Ok(Outcome::Exit(code)) => return Some(code),
+            Err(error) => {
+                let _ = writeln!(output, "{error}");
+            }
+        }
+        None
+    }
+}
+
+/// Whether the input leaves brackets, strings or comments open, more lines being needed.
+fn is_incomplete(input: &str) -> bool {
+    // the closing character expected for every open construct, `*` standing for block comments
+    let mut open: Vec<char> = Vec::new();
+    let mut characters = input.chars().peekable();
+    while let Some(c) = characters.next() {
+        match open.last() {
+            Some('*') => {
+                if c == '*' && characters.next_if_eq(&'/').is_some() {
+                    open.pop();
+                }
+            }
+            Some('"') => match c {
+                '\\' => {
+                    characters.next();
+                }
+                '"' => {
+                    open.pop();
+                }
+                '$' if characters.next_if_eq(&'{').is_some() => open.push('}'),
+                _ => (),
+            },
+            _ => match c {
+                '"' => open.push('"'),
+                '/' if characters.next_if_eq(&'/').is_some() => {
+                    while characters.next_if(|c| *c != '\n').is_some() {}
+                }
+                '/' if characters.next_if_eq(&'*').is_some() => open.push('*'),
+                '(' => open.push(')'),
+                '[' => open.push(']'),
+                '{' => open.push('}'),
+                // extra closing brackets are left for the parser to report
+                ')' | ']' | '}' if open.pop().is_none() => return false,
+                _ => (),
+            },
+        }
+    }
+    !open.is_empty()
+}
+
+#[test]
+fn test_is_incomplete() {
+    assert!(!is_incomplete("a: 1"));
+    assert!(is_incomplete("f: (a) => {"));
+    assert!(!is_incomplete("f: (a) => {\n  a\n}"));
+    assert!(is_incomplete(r#"s: "${ {"#));
+    assert!(!is_incomplete(r#"s: "} ${ "{" } \" (""#));
+    assert!(!is_incomplete("a // {"));
+    assert!(is_incomplete("a /* {"));
+    assert!(!is_incomplete("a }"));
+}
+
+#[test]
+fn test_repl_lines() {
+    let engine = Engine::new();
+    let mut repl = Repl::new(&engine);
+    let mut output = Vec::new();
+    for line in [
+        "double: (n) => {",
+        "  n * 2",
+        "}",
+        "double(21)",
+        r#"name: "porte""#,
+        ":env",
+        "none",
+        ":reset",
+        ":env",
+        "add(1, true)",
+    ] {
+        assert_eq!(repl.line(line, &mut output), None);
+    }
+    assert_eq!(repl.line("exit(3)", &mut output), Some(3));
+    let output = String::from_utf8(output).unwrap();
+    let mut lines = output.lines();
+    assert_eq!(lines.next(), Some("<function/1>"));
+    assert_eq!(lines.next(), Some("42"));
+    assert_eq!(lines.next(), Some(r#""porte""#));
+    assert_eq!(lines.next(), Some("double: <function/1>"));
+    assert_eq!(lines.next(), Some(r#"name: "porte""#));
+    assert!(lines.next().unwrap().contains("type error"));
+}
@@ -1,15 +1,18 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, mem, rc::Rc};
 
 use crate::{
+    error::{Error, TraceEntry},
     execution_tree::{
-        Cond, Expr, ExprInner, FnCall, FnDef, FnRet, Id, Literal, Loop, LoopBr, Program, Scope,
-        VarAssign, VarCall, VarDef,
+        Cond, Expr, ExprInner, FieldAssign, FieldCall, FnCall, FnDef, FnRet, Id, List, Literal,
+        Loop, LoopBr, Object, Program, Scope, VarAssign, VarCall, VarDef,
     },
+    source::Span,
     value::{
         function::{
-            ConstructedFunctionExecutor, Function, FunctionExecutor, NativeFunctionExecutor,
+            ConstructedFunctionExecutor, Function, FunctionExecutor, HigherOrderFunctionExecutor,
+            NativeFunctionExecutor,
         },
-        Value,
+        ObjectRef, Value,
     },
 };
 
@@ -28,9 +31,16 @@ impl FrameBuilder {
     }
 }
 
+/// A variable shared between the frame declaring it and the closures using it.
+pub type VariableRef = Rc<RefCell<Value>>;
+
+fn new_variable(value: Value) -> VariableRef {
+    Rc::new(RefCell::new(value))
+}
+
+#[derive(Debug)]
 pub struct Frame {
-    _scope_id: Id,
-    variables: HashMap<Id, Value>,
+    variables: HashMap<Id, VariableRef>,
 }
 
 impl Frame {
@@ -40,9 +50,7 @@ impl Frame {
         F: FnOnce(&mut FrameBuilder),
     {
         let Scope {
-            scope_id,
-            local_variables,
-            ..
+            local_variables, ..
         } = scope;
 
         let mut frame_builder = FrameBuilder::new();
@@ -52,22 +60,70 @@ impl Frame {
         builder(&mut frame_builder);
 
         let FrameBuilder { variables } = frame_builder;
-        let scope_id = *scope_id;
+        let variables = variables
+            .into_iter()
+            .map(|(id, value)| (id, new_variable(value)))
+            .collect();
+        Self { variables }
+    }
+
+    /// Holds the variables a closure captured, shared with the frames they come from.
+    pub fn captured(environment: Environment) -> Self {
+        let variables = environment;
+        Self { variables }
+    }
+}
+
+/// A function call in progress, kept to report porte-level stack traces.
+pub struct Call {
+    function_name: String,
+    call_site: Span,
+}
+
+impl Call {
+    pub fn new(function_name: String, call_site: Span) -> Self {
         Self {
-            _scope_id: scope_id,
-            variables,
+            function_name,
+            call_site,
         }
     }
 }
 
+/// The variables a closure uses from the scopes enclosing its definition. Those of the
+/// outermost frame are not captured but read from the stack, which keeps that frame as long as
+/// the runtime, so that functions stored there do not keep themselves alive.
+pub type Environment = HashMap<Id, VariableRef>;
+
 pub struct Stack {
     frames: Vec<Frame>,
+    calls: Vec<Call>,
 }
 
 impl Stack {
     pub fn new() -> Self {
         let frames = Vec::new();
-        Self { frames }
+        let calls = Vec::new();
+        Self { frames, calls }
+    }
+
+    pub fn push_call(&mut self, call: Call) {
+        self.calls.push(call);
+    }
+
+    pub fn pop_call(&mut self) {
+        let _dropped = self.calls.pop();
+    }
+
+    /// The calls in progress, innermost first.
+    pub fn trace(&self, program: &Program) -> Vec<TraceEntry> {
+        self.calls
+            .iter()
+            .rev()
+            .map(|call| TraceEntry {
+                function_name: call.function_name.clone(),
+                call_site: program.sources.locate(&call.call_site),
+            })
+            .collect()
     }
 
     pub fn push_frame(&mut self, frame: Frame) {
@@ -78,24 +134,56 @@ impl Stack {
         let _dropped = self.frames.pop();
     }
 
-    pub fn get(&self, variable_id: &Id) -> Option<&Value> {
-        self.frames.iter().rev().find_map(|frame| {
-            frame
-                .variables
-                .iter()
-                .find(|(id, _value)| **id == *variable_id)
-                .map(|(_id, value)| value)
-        })
+    /// The variables of the frames currently visible, to be captured by a closure using them.
+    pub fn environment(&self, variable_ids: &[Id]) -> Environment {
+        let Some((_outermost, frames)) = self.frames.split_first() else {
+            return Environment::new();
+        };
+        variable_ids
+            .iter()
+            .filter_map(|id| {
+                let variable = frames
+                    .iter()
+                    .rev()
+                    .find_map(|frame| frame.variables.get(id))?;
+                Some((*id, variable.clone()))
+            })
+            .collect()
     }
 
-    pub fn get_mut(&mut self, variable_id: &Id) -> Option<&mut Value> {
-        self.frames.iter_mut().rev().find_map(|frame| {
-            frame
-                .variables
-                .iter_mut()
-                .find(|(id, _value)| **id == *variable_id)
-                .map(|(_id, value)| value)
-        })
+    /// Makes only the outermost frame and a captured environment visible, returning the frames
+    /// it replaces.
+    pub fn replace_environment(&mut self, environment: Environment) -> Vec<Frame> {
+        let mut frames = mem::take(&mut self.frames);
+        let rest = frames.split_off(frames.len().min(1));
+        self.frames = frames;
+        self.frames.push(Frame::captured(environment));
+        rest
+    }
+
+    /// Makes the frames replaced by `replace_environment` visible again.
+    pub fn restore_environment(&mut self, frames: Vec<Frame>) {
+        let _captured = self.frames.pop();
+        self.frames.extend(frames);
+    }
+
+    fn variable_ref(&self, variable_id: &Id) -> Option<&VariableRef> {
+        self.frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.variables.get(variable_id))
+    }
+
+    pub fn get(&self, variable_id: &Id) -> Option<Value> {
+        let variable = self.variable_ref(variable_id)?;
+        Some(variable.borrow().clone())
+    }
+
+    /// Overwrites a variable of the innermost frame declaring it, fails if none does.
+    pub fn set(&mut self, variable_id: &Id, value: Value) -> Option<()> {
+        let variable = self.variable_ref(variable_id)?;
+        *variable.borrow_mut() = value;
+        Some(())
     }
 }
 
@@ -113,6 +201,8 @@ pub struct ShortCircuit {
 pub enum ExecReturn {
     Value(Value),
     ShortCircuit(ShortCircuit),
+    /// The script called `exit` with that status, unwinding every scope.
+    Exit(i32),
 }
 
 impl ExecReturn {
@@ -150,6 +240,45 @@ impl From<Value> for ExecReturn {
     }
 }
 
+/// How a program ended.
+#[derive(Debug)]
+pub enum Outcome {
+    /// It ran to its end, giving the value of its last expression.
+    Value(Value),
+    /// It called `exit` with that status.
+    Exit(i32),
+}
+
+impl Outcome {
+    /// The final value, an exit giving its status.
+    pub fn into_value(self) -> Value {
+        match self {
+            Self::Value(value) => value,
+            Self::Exit(code) => i64::from(code).into(),
+        }
+    }
+}
+
+/// Lets a higher-order native function call the porte functions it received.
+pub struct Caller<'a> {
+    runtime: &'a mut Runtime,
+    program: &'a Program,
+}
+
+impl Caller<'_> {
+    pub fn call(&mut self, function: &Function, arguments: Vec<Value>) -> Result<Value, Error> {
+        let returned = self
+            .runtime
+            .execute_function(arguments, function, self.program)?;
+        match returned {
+            ExecReturn::Value(value) => Ok(value),
+            // native functions only return values or errors, the call site turns this one back
+            ExecReturn::Exit(code) => Err(Error::Exit(code)),
+            ExecReturn::ShortCircuit(_) => unreachable!("functions return their short circuits"),
+        }
+    }
+}
+
 pub struct Runtime {
     stack: Stack,
 }
@@ -160,10 +289,45 @@ impl Runtime {
         Self { stack }
     }
 
-    pub fn execute(&mut self, program: &Program) -> Value {
-        self.execute_scope(&program.main_scope_id, program, |_| ())
-            .into_value()
-            .unwrap()
+    pub fn execute(&mut self, program: &Program) -> Result<Outcome, Error> {
+        let returned = self.execute_scope(&program.main_scope_id, program, |_| ())?;
+        match returned {
+            ExecReturn::Value(value) => Ok(Outcome::Value(value)),
+            ExecReturn::ShortCircuit(ShortCircuit { value, .. }) => Ok(Outcome::Value(value)),
+            ExecReturn::Exit(code) => Ok(Outcome::Exit(code)),
+        }
+    }
+
+    /// Runs the main scope in a frame kept for the next calls, the variables it defines staying
+    /// visible to the programs continuing this one.
+    pub fn execute_persistent(&mut self, program: &Program) -> Result<Outcome, Error> {
+        let scope = program.scopes.get(&program.main_scope_id).unwrap();
+        match self.stack.frames.first_mut() {
+            Some(frame) => {
+                for variable in &scope.local_variables {
+                    let entry = frame.variables.entry(*variable);
+                    entry.or_insert_with(|| new_variable(Value::None));
+                }
+            }
+            None => self.stack.push_frame(Frame::new(scope, |_| ())),
+        }
+
+        let mut last_expression = Value::None;
+        for expression in &scope.expressions {
+            match self.execute_expression(expression, program)? {
+                ExecReturn::Value(value) => last_expression = value,
+                ExecReturn::ShortCircuit(ShortCircuit { value, .. }) => {
+                    return Ok(Outcome::Value(value))
+                }
+                ExecReturn::Exit(code) => return Ok(Outcome::Exit(code)),
+            }
+        }
+        Ok(Outcome::Value(last_expression))
+    }
+
+    /// The value of a variable of the frames currently visible.
+    pub fn variable(&self, variable_id: &Id) -> Option<Value> {
+        self.stack.get(variable_id)
     }
 
     pub fn execute_scope<F>(
@@ -171,7 +335,7 @@ impl Runtime {
         scope_id: &Id,
         program: &Program,
         frame_builder: F,
-    ) -> ExecReturn
+    ) -> Result<ExecReturn, Error>
     where
         F: FnOnce(&mut FrameBuilder),
     {
@@ -186,26 +350,42 @@ impl Runtime {
 
         let mut last_expression = Value::None;
         for expression in expressions {
-            let returned = self.execute_expression(expression, program);
-            match returned {
-                ExecReturn::ShortCircuit(short_circuit) => {
+            let returned = match self.execute_expression(expression, program) {
+                Ok(returned) => returned,
+                Err(error) => {
                     self.stack.pop_frame();
-                    return ExecReturn::ShortCircuit(short_circuit);
+                    return Err(error);
                 }
+            };
+            match returned {
                 ExecReturn::Value(value) => {
                     last_expression = value;
                 }
+                returned => {
+                    self.stack.pop_frame();
+                    return Ok(returned);
+                }
             }
         }
 
         self.stack.pop_frame();
-        last_expression.into()
+        Ok(last_expression.into())
     }
 
-    pub fn execute_expression(&mut self, expression: &Expr, program: &Program) -> ExecReturn {
-        match expression.inner() {
+    pub fn execute_expression(
+        &mut self,
+        expression: &Expr,
+        program: &Program,
+    ) -> Result<ExecReturn, Error> {
+        let returned = match expression.inner() {
             ExprInner::Scope(scope_id) => self.execute_scope(scope_id, program, |_| ()),
             ExprInner::Literal(literal) => self.execute_literal(literal),
+            ExprInner::List(list) => self.execute_list(list, program),
+            ExprInner::Object(object) => self.execute_object(object, program),
+            ExprInner::FieldCall(field_call) => self.execute_field_call(field_call, program),
+            ExprInner::FieldAssign(field_assignment) => {
+                self.execute_field_assignment(field_assignment, program)
+            }
             ExprInner::VarDef(variable_definition) => {
                 self.execute_variable_definition(variable_definition, program)
             }
@@ -216,105 +396,214 @@ impl Runtime {
             ExprInner::FnDef(function_definition) => {
                 self.execute_function_definition(function_definition)
             }
-            ExprInner::FnCall(function_call) => self.execute_function_call(function_call, program),
+            ExprInner::FnCall(function_call) => {
+                self.execute_function_call(function_call, expression.span(), program)
+            }
             ExprInner::FnRet(function_return) => {
                 self.execute_function_return(function_return, program)
             }
             ExprInner::Loop(loop_) => self.execute_loop(loop_, program),
             ExprInner::LoopBr(loop_break) => self.execute_loop_break(loop_break, program),
             ExprInner::Cond(condition) => self.execute_condition(condition, program),
-        }
+        };
+        returned.map_err(|error| error.at(program.sources.locate(expression.span())))
     }
 
-    pub fn execute_literal(&self, literal: &Literal) -> ExecReturn {
+    pub fn execute_literal(&self, literal: &Literal) -> Result<ExecReturn, Error> {
         let Literal(value) = literal;
-        ExecReturn::new_value(value.clone())
+        Ok(ExecReturn::new_value(value.clone()))
+    }
+
+    pub fn execute_list(&mut self, list: &List, program: &Program) -> Result<ExecReturn, Error> {
+        let List { elements } = list;
+        let mut collector = Vec::new();
+        for element in elements {
+            match self.execute_expression(element, program)? {
+                ExecReturn::Value(value) => collector.push(value),
+                returned => return Ok(returned),
+            }
+        }
+        Ok(Value::List(collector).into())
+    }
+
+    pub fn execute_object(
+        &mut self,
+        object: &Object,
+        program: &Program,
+    ) -> Result<ExecReturn, Error> {
+        let Object { fields } = object;
+        let mut collector = HashMap::new();
+        for (name, value) in fields {
+            match self.execute_expression(value, program)? {
+                ExecReturn::Value(value) => collector.insert(name.clone(), value),
+                returned => return Ok(returned),
+            };
+        }
+        Ok(Value::from(collector).into())
+    }
+
+    /// Reads a field, `none` when the object does not have it.
+    pub fn execute_field_call(
+        &mut self,
+        field_call: &FieldCall,
+        program: &Program,
+    ) -> Result<ExecReturn, Error> {
+        let FieldCall { object, field } = field_call;
+        let object = match self.execute_expression(object, program)? {
+            ExecReturn::Value(value) => value,
+            returned => return Ok(returned),
+        };
+        match object {
+            Value::Object(object) => Ok(Value::from(object.borrow().get(field).cloned()).into()),
+            other => Err(Error::new_type(format!(
+                "reading field '{field}' of {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    pub fn execute_field_assignment(
+        &mut self,
+        field_assignment: &FieldAssign,
+        program: &Program,
+    ) -> Result<ExecReturn, Error> {
+        let FieldAssign {
+            variable_id,
+            path,
+            value,
+        } = field_assignment;
+        let value = match self.execute_expression(value, program)? {
+            ExecReturn::Value(value) => value,
+            returned => return Ok(returned),
+        };
+        // objects are shared, so updating the innermost one is visible through the variable
+        let (field, parents) = path.split_last().expect("field paths are never empty");
+        let mut object = self.stack.get(variable_id).ok_or_else(unbound_variable)?;
+        for parent in parents {
+            let inner = assigned_object(object, parent)?
+                .borrow()
+                .get(parent)
+                .cloned();
+            object = inner.into();
+        }
+        assigned_object(object, field)?
+            .borrow_mut()
+            .insert(field.clone(), value.clone());
+        Ok(value.into())
     }
 
     pub fn execute_variable_definition(
         &mut self,
         variable_definition: &VarDef,
         program: &Program,
-    ) -> ExecReturn {
+    ) -> Result<ExecReturn, Error> {
         let VarDef { variable_id, value } = variable_definition;
-        let value = match self.execute_expression(value, program) {
-            ExecReturn::ShortCircuit(short_circuit) => {
-                return ExecReturn::ShortCircuit(short_circuit);
-            }
+        let value = match self.execute_expression(value, program)? {
             ExecReturn::Value(value) => value,
+            returned => return Ok(returned),
         };
-        let variable = self.stack.get_mut(variable_id).unwrap();
-        *variable = value.clone();
-        value.into()
+        self.stack
+            .set(variable_id, value.clone())
+            .ok_or_else(unbound_variable)?;
+        Ok(value.into())
     }
 
     pub fn execute_variable_assignment(
         &mut self,
         variable_assignment: &VarAssign,
         program: &Program,
-    ) -> ExecReturn {
+    ) -> Result<ExecReturn, Error> {
         let VarAssign { variable_id, value } = variable_assignment;
-        let value = match self.execute_expression(value, program) {
+        let value = match self.execute_expression(value, program)? {
             ExecReturn::Value(value) => value,
-            ExecReturn::ShortCircuit(short_circuit) => {
-                return ExecReturn::ShortCircuit(short_circuit);
-            }
+            returned => return Ok(returned),
         };
-        let variable = self.stack.get_mut(variable_id).unwrap();
-        *variable = value.clone();
-        value.into()
+        self.stack
+            .set(variable_id, value.clone())
+            .ok_or_else(unbound_variable)?;
+        Ok(value.into())
     }
 
-    pub fn execute_variable_call(&mut self, variable_call: &VarCall) -> ExecReturn {
+    pub fn execute_variable_call(&mut self, variable_call: &VarCall) -> Result<ExecReturn, Error> {
         let VarCall { variable_id } = variable_call;
-        let variable = self.stack.get(variable_id).unwrap();
-        variable.clone().into()
+        let variable = self.stack.get(variable_id).ok_or_else(unbound_variable)?;
+        Ok(variable.into())
     }
 
-    pub fn execute_function_definition(&mut self, function_definition: &FnDef) -> ExecReturn {
+    pub fn execute_function_definition(
+        &mut self,
+        function_definition: &FnDef,
+    ) -> Result<ExecReturn, Error> {
         let FnDef {
             parameter_ids: argument_ids,
             body_scope_id,
+            captured_ids,
+            self_id,
         } = function_definition;
-        let value = Function::new_constructed(argument_ids.clone(), *body_scope_id);
+        let environment = self.stack.environment(captured_ids);
+        let value =
+            Function::new_constructed(argument_ids.clone(), *body_scope_id, environment, *self_id);
         let value = Value::Function(value);
-        value.into()
+        Ok(value.into())
     }
 
     pub fn execute_function_call(
         &mut self,
         function_call: &FnCall,
+        call_site: &Span,
         program: &Program,
-    ) -> ExecReturn {
+    ) -> Result<ExecReturn, Error> {
         let FnCall {
-            variable_id,
+            function_name,
+            callee,
             arguments,
         } = function_call;
 
+        let function = match self.execute_expression(callee, program)? {
+            ExecReturn::Value(Value::Function(function)) => function,
+            ExecReturn::Value(value) => {
+                return Err(Error::new_type(format!(
+                    "calling a non-function value, found {}",
+                    value.type_name()
+                )))
+            }
+            returned => return Ok(returned),
+        };
+
         let mut collector = Vec::new();
         for argument in arguments {
-            match self.execute_expression(argument, program) {
+            match self.execute_expression(argument, program)? {
                 ExecReturn::Value(value) => collector.push(value),
-                ExecReturn::ShortCircuit(short_circuit) => {
-                    return ExecReturn::ShortCircuit(short_circuit)
-                }
+                returned => return Ok(returned),
             }
         }
         let arguments = collector;
 
-        let function = self
-            .stack
-            .get(variable_id)
-            .unwrap()
-            .as_function()
-            .expect("calling a non-function variable")
-            .clone();
+        let call = Call::new(function_name.clone(), call_site.clone());
+        self.stack.push_call(call);
+        let returned = self
+            .execute_function(arguments, &function, program)
+            .map_err(|error| error.traced(|| self.stack.trace(program)));
+        self.stack.pop_call();
+        returned
+    }
+
+    pub fn execute_function(
+        &mut self,
+        arguments: Vec<Value>,
+        function: &Function,
+        program: &Program,
+    ) -> Result<ExecReturn, Error> {
+        if arguments.len() != function.argument_count {
+            return Err(Error::new_arity(function.argument_count, arguments.len()));
+        }
         match function.executor() {
-            FunctionExecutor::Constructed(executor) => self
-                .execute_constructed_function(arguments, executor, program)
-                .into(),
-            FunctionExecutor::Native(executor) => {
-                self.execute_native_function(arguments, executor).into()
+            FunctionExecutor::Constructed(executor) => {
+                self.execute_constructed_function(arguments, function, executor, program)
+            }
+            FunctionExecutor::Native(executor) => self.execute_native_function(arguments, executor),
+            FunctionExecutor::HigherOrder(executor) => {
+                self.execute_higher_order_function(arguments, executor, program)
             }
         }
     }
@@ -322,26 +611,38 @@ impl Runtime {
     pub fn execute_constructed_function(
         &mut self,
         arguments: Vec<Value>,
+        function: &Function,
         executor: &ConstructedFunctionExecutor,
         program: &Program,
-    ) -> Value {
+    ) -> Result<ExecReturn, Error> {
         let ConstructedFunctionExecutor {
             parameter_ids,
             body_scope_id,
+            environment,
+            self_id,
         } = executor;
 
+        // the body sees the variables captured at definition, not the caller's
+        let mut environment = environment.clone();
+        if let Some(self_id) = self_id {
+            let function = Value::Function(function.clone());
+            environment.insert(*self_id, new_variable(function));
+        }
+        let caller_frames = self.stack.replace_environment(environment);
         let returned = self.execute_scope(body_scope_id, program, |builder| {
-            for (id, argument) in parameter_ids.iter().zip(arguments.into_iter()) {
+            for (id, argument) in parameter_ids.iter().zip(arguments) {
                 builder.variable(id, argument)
             }
         });
+        self.stack.restore_environment(caller_frames);
+        let returned = returned?;
 
         match returned {
-            ExecReturn::Value(value) => value,
             ExecReturn::ShortCircuit(ShortCircuit {
                 value,
                 destination_scope_id: _,
-            }) => value,
+            }) => Ok(value.into()),
+            returned => Ok(returned),
         }
     }
 
@@ -349,77 +650,93 @@ impl Runtime {
         &mut self,
         arguments: Vec<Value>,
         executor: &NativeFunctionExecutor,
-    ) -> Value {
-        (executor.closure)(arguments)
+    ) -> Result<ExecReturn, Error> {
+        native_returned((executor.closure)(arguments))
+    }
+
+    pub fn execute_higher_order_function(
+        &mut self,
+        arguments: Vec<Value>,
+        executor: &HigherOrderFunctionExecutor,
+        program: &Program,
+    ) -> Result<ExecReturn, Error> {
+        let mut caller = Caller {
+            runtime: self,
+            program,
+        };
+        native_returned((executor.closure)(arguments, &mut caller))
     }
 
     pub fn execute_function_return(
         &mut self,
         function_return: &FnRet,
         program: &Program,
-    ) -> ExecReturn {
+    ) -> Result<ExecReturn, Error> {
         let FnRet {
             value,
             function_scope_id,
         } = function_return;
 
-        let value = match self.execute_expression(value, program) {
+        let value = match self.execute_expression(value, program)? {
             ExecReturn::Value(value) => value,
             ExecReturn::ShortCircuit(ShortCircuit {
                 value,
                 destination_scope_id: _,
             }) => value,
+            exit @ ExecReturn::Exit(_) => return Ok(exit),
         };
 
-        ExecReturn::new_short_circuit(value, *function_scope_id)
+        Ok(ExecReturn::new_short_circuit(value, *function_scope_id))
     }
 
-    pub fn execute_loop(&mut self, loop_: &Loop, program: &Program) -> ExecReturn {
+    pub fn execute_loop(&mut self, loop_: &Loop, program: &Program) -> Result<ExecReturn, Error> {
         let Loop { body_scope_id } = loop_;
 
         loop {
-            let value = self.execute_scope(body_scope_id, program, |_| ());
+            let value = self.execute_scope(body_scope_id, program, |_| ())?;
             match value {
                 ExecReturn::ShortCircuit(ShortCircuit {
                     value,
                     destination_scope_id,
-                }) if destination_scope_id == *body_scope_id => return value.into(),
-                ExecReturn::ShortCircuit(short_circuit) => {
-                    return ExecReturn::ShortCircuit(short_circuit)
-                }
-                _ => (),
+                }) if destination_scope_id == *body_scope_id => return Ok(value.into()),
+                ExecReturn::Value(_) => (),
+                returned => return Ok(returned),
             }
         }
     }
 
-    pub fn execute_loop_break(&mut self, loop_break: &LoopBr, program: &Program) -> ExecReturn {
+    pub fn execute_loop_break(
+        &mut self,
+        loop_break: &LoopBr,
+        program: &Program,
+    ) -> Result<ExecReturn, Error> {
         let LoopBr {
             value,
             loop_scope_id,
         } = loop_break;
 
-        let value = match self.execute_expression(value, program) {
+        let value = match self.execute_expression(value, program)? {
             ExecReturn::Value(value) => value,
-            ExecReturn::ShortCircuit(short_circuit) => {
-                return ExecReturn::ShortCircuit(short_circuit)
-            }
+            returned => return Ok(returned),
         };
 
-        ExecReturn::new_short_circuit(value, *loop_scope_id)
+        Ok(ExecReturn::new_short_circuit(value, *loop_scope_id))
     }
 
-    pub fn execute_condition(&mut self, condition: &Cond, program: &Program) -> ExecReturn {
+    pub fn execute_condition(
+        &mut self,
+        condition: &Cond,
+        program: &Program,
+    ) -> Result<ExecReturn, Error> {
         let Cond {
             condition,
             arm_true,
             arm_false,
         } = condition;
 
-        let value = match self.execute_expression(condition, program) {
+        let value = match self.execute_expression(condition, program)? {
             ExecReturn::Value(value) => value,
-            ExecReturn::ShortCircuit(short_circuit) => {
-                return ExecReturn::ShortCircuit(short_circuit)
-            }
+            returned => return Ok(returned),
         };
 
         if let Value::Bool(boolean) = value {
@@ -429,16 +746,107 @@ impl Runtime {
                 arm_false
                     .as_ref()
                     .map(|arm_false| self.execute_expression(arm_false, program))
-                    .unwrap_or(Value::Bool(false).into())
+                    .unwrap_or(Ok(Value::Bool(false).into()))
             }
         } else {
-            panic!("non-boolean in condition");
+            Err(Error::new_type(format!(
+                "non-boolean in condition, found {}",
+                value.type_name()
+            )))
         }
     }
 }
 
+fn assigned_object(value: Value, field: &str) -> Result<ObjectRef, Error> {
+    match value {
+        Value::Object(object) => Ok(object),
+        other => Err(Error::new_type(format!(
+            "assigning field '{field}' of {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Native functions stop the script by returning `Error::Exit`, which resumes unwinding the
+/// porte scopes from their call site.
+fn native_returned(returned: Result<Value, Error>) -> Result<ExecReturn, Error> {
+    match returned {
+        Err(Error::Exit(code)) => Ok(ExecReturn::Exit(code)),
+        returned => returned.map(ExecReturn::from),
+    }
+}
+
+fn unbound_variable() -> Error {
+    Error::new_resolve("variable is not bound in the current stack")
+}
+
 impl Default for Runtime {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[test]
+fn test_closures() {
+    use crate::engine::eval;
+    let text = "counter: () => { count: 0; () => { count <- count + 1 } };
+        a: counter(); b: counter();
+        a(); a(); b();
+        a() * 10 + b()";
+    assert_eq!(eval(text).as_number().unwrap(), 32.);
+    let text = "adder: (n) => { (x) => { x + n } };
+        apply: (f, x) => { n: 100; f(x) };
+        apply(adder(2), 1)";
+    assert_eq!(eval(text).as_number().unwrap(), 3.);
+    let text = "fact: none;
+        fact <- (n) => { if n <= 1 1 else n * fact(n - 1) };
+        fact(5)";
+    assert_eq!(eval(text).as_number().unwrap(), 120.);
+    let text =
+        "make: () => { go: none; go <- (n) => { if n == 0 0 else go(n - 1) }; go }; make()(3)";
+    assert_eq!(eval(text).as_int(), Some(0));
+
+    // closures stored where the variables they use live do not keep them alive once run
+    let texts = [
+        "o: obj(); f: () => { o }; o",
+        "keep: () => { o: obj(); f: () => { o }; f() }; keep()",
+        "o: obj(); f: none; f <- (n) => { if n == 0 o else f(n - 1) }; f(3)",
+        "{ o: obj(); f: () => { o }; g: () => { f() }; g() }",
+        "keep: () => { o: obj(); f: none; f <- (n) => { if n == 0 o else f(n - 1) }; f(3) }; keep()",
+    ];
+    for text in texts {
+        let Value::Object(object) = eval(text) else {
+            panic!("expected an object from '{text}'");
+        };
+        assert_eq!(Rc::strong_count(&object), 1, "'{text}' leaked");
+    }
+}
+
+#[test]
+fn test_callee_expressions() {
+    use crate::engine::{eval, Engine};
+    let text = "make_adder: (n) => { (x) => { x + n } }; make_adder(1)(2)";
+    assert_eq!(eval(text).as_number().unwrap(), 3.);
+    assert_eq!(eval("((x) => { x * 2 })(3)").as_number().unwrap(), 6.);
+    let text = r#"o: set(obj(), "handler", (x) => { x - 1 }); get(o, "handler")(5)"#;
+    assert_eq!(eval(text).as_number().unwrap(), 4.);
+    let error = Engine::new().eval("a: 1; a(2)").unwrap_err();
+    assert!(matches!(error.inner(), Error::Type(_)));
+}
+
+#[test]
+fn test_objects() {
+    use crate::engine::{eval, Engine};
+    let text =
+        r#"o: { name: "x", size: 1 + 2, inner: { depth: 1 }, }; o.size * 10 + o.inner.depth"#;
+    assert_eq!(eval(text).as_number().unwrap(), 31.);
+    let text =
+        "o: { inner: { depth: 1 } }; o.inner.depth <- 2; o.extra <- 3; o.inner.depth + o.extra";
+    assert_eq!(eval(text).as_number().unwrap(), 5.);
+    let text = "o: { f: (x) => { x + 1 } }; o.f(1) + { a: 1 }.a";
+    assert_eq!(eval(text).as_number().unwrap(), 3.);
+    assert!(matches!(eval("{ a: 1 }.b"), Value::None));
+    assert_eq!(eval("{ a: 1; a + 1 }").as_number().unwrap(), 2.);
+    let error = Engine::new().eval("a: 1; a.b <- 2").unwrap_err();
+    assert!(matches!(error.inner(), Error::Type(_)));
+}
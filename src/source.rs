@@ -0,0 +1,151 @@
+use std::{fmt, ops::Range, rc::Rc};
+
+/// Offsets of the first and past-the-end characters of a node in its source text.
+pub type Span = Range<usize>;
+
+/// A named source text, able to translate spans into human readable locations.
+#[derive(Debug)]
+pub struct Source {
+    name: Rc<str>,
+    text: String,
+    line_starts: Vec<usize>,
+    offset: usize,
+}
+
+impl Source {
+    pub fn new<S: ToString, T: ToString>(name: S, text: T) -> Self {
+        let name = name.to_string().into();
+        let text = text.to_string();
+        let line_starts = text
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| *c == '\n')
+            .map(|(index, _)| index + 1);
+        let line_starts = [0].into_iter().chain(line_starts).collect();
+        Self {
+            name,
+            text,
+            line_starts,
+            offset: 0,
+        }
+    }
+
+    /// Places the text after `offset` characters of other sources, spans of its nodes starting
+    /// from there.
+    pub fn with_offset(self, offset: usize) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The offset past the end of input, where another source can be placed.
+    pub fn end(&self) -> usize {
+        self.offset + self.text.chars().count() + 1
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The text of a one-based line, without its line break.
+    pub fn line(&self, line: usize) -> &str {
+        self.text.lines().nth(line - 1).unwrap_or_default()
+    }
+
+    pub fn locate(&self, span: &Span) -> Location {
+        let start = span.start.saturating_sub(self.offset);
+        let line = self.line_starts.partition_point(|line| *line <= start);
+        let column = start - self.line_starts[line - 1] + 1;
+        Location {
+            file: self.name.clone(),
+            line,
+            column,
+        }
+    }
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self::new("<unknown>", "")
+    }
+}
+
+/// The sources a program was parsed from, laid end to end.
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+    sources: Vec<Rc<Source>>,
+}
+
+impl Sources {
+    pub fn new(source: Rc<Source>) -> Self {
+        let sources = vec![source];
+        Self { sources }
+    }
+
+    /// Adds a source, which must be placed after the others.
+    pub fn push(&mut self, source: Rc<Source>) {
+        self.sources.push(source);
+    }
+
+    /// Adds sources placed after these ones.
+    pub fn append(&mut self, sources: Sources) {
+        self.sources.extend(sources.sources);
+    }
+
+    /// The offset where another source can be placed.
+    pub fn end(&self) -> usize {
+        self.sources.last().map(|source| source.end()).unwrap_or(0)
+    }
+
+    pub fn locate(&self, span: &Span) -> Location {
+        match self
+            .sources
+            .iter()
+            .rev()
+            .find(|source| source.offset() <= span.start)
+        {
+            Some(source) => source.locate(span),
+            None => Source::default().locate(span),
+        }
+    }
+}
+
+/// One-based line and column of a position in a named source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: Rc<str>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { file, line, column } = self;
+        write!(f, "{file}:{line}:{column}")
+    }
+}
+
+#[test]
+fn test_source_locate() {
+    let source = Source::new("test.pr", "a: 3;\nb: 4;\n\nc");
+    assert_eq!(source.locate(&(0..1)).to_string(), "test.pr:1:1");
+    assert_eq!(source.locate(&(9..10)).to_string(), "test.pr:2:4");
+    assert_eq!(source.locate(&(13..14)).to_string(), "test.pr:4:1");
+}
+
+#[test]
+fn test_sources_locate() {
+    let main = Rc::new(Source::new("main.pr", "a: 3;\nb"));
+    let module = Source::new("lib.pr", "c: 4;\nd").with_offset(main.end());
+    let mut sources = Sources::new(main);
+    sources.push(Rc::new(module));
+    assert_eq!(sources.locate(&(6..7)).to_string(), "main.pr:2:1");
+    assert_eq!(sources.locate(&(8..9)).to_string(), "lib.pr:1:1");
+    assert_eq!(sources.locate(&(14..15)).to_string(), "lib.pr:2:1");
+}
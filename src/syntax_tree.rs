@@ -1,82 +1,148 @@
-use crate::value::Value;
+use crate::{
+    source::{Sources, Span},
+    value::Value,
+};
 
 #[derive(Debug)]
 pub struct Program {
     pub body: Scope,
+    pub sources: Sources,
+}
+
+impl Program {
+    pub fn new(body: Scope) -> Self {
+        let sources = Sources::default();
+        Self { body, sources }
+    }
 }
 
 #[derive(Debug)]
-pub struct Expr(pub Box<ExprInner>);
+pub struct Expr {
+    pub inner: Box<ExprInner>,
+    pub span: Span,
+}
 
 impl Expr {
     pub fn new(inner: ExprInner) -> Self {
-        Self(Box::new(inner))
+        Self {
+            inner: Box::new(inner),
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        let Self { inner, .. } = self;
+        Self { inner, span }
     }
 
     pub fn inner(&self) -> &ExprInner {
-        let Self(inner) = self;
-        inner
+        &self.inner
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
     }
 
     pub fn into_inner(self) -> ExprInner {
-        let Self(inner) = self;
+        let Self { inner, .. } = self;
         *inner
     }
 
+    pub fn into_parts(self) -> (ExprInner, Span) {
+        let Self { inner, span } = self;
+        (*inner, span)
+    }
+
     pub fn new_scope(instructions: Vec<Expr>) -> Self {
-        Self(Box::new(ExprInner::Scope(Scope { instructions })))
+        Self::new(ExprInner::Scope(Scope { instructions }))
     }
 
     pub fn new_literal<V: Into<Value>>(value: V) -> Self {
-        Self(Box::new(ExprInner::Literal(Literal(value.into()))))
+        Self::new(ExprInner::Literal(Literal(value.into())))
+    }
+
+    pub fn new_list(elements: Vec<Expr>) -> Self {
+        Self::new(ExprInner::List(List { elements }))
+    }
+
+    pub fn new_object(fields: Vec<(String, Expr)>) -> Self {
+        Self::new(ExprInner::Object(Object { fields }))
+    }
+
+    pub fn new_field_call<S: ToString>(object: Expr, field: S) -> Self {
+        let field = field.to_string();
+        Self::new(ExprInner::FieldCall(FieldCall { object, field }))
+    }
+
+    pub fn new_field_assignment<S: ToString>(name: S, path: Vec<String>, value: Expr) -> Self {
+        let name = name.to_string();
+        Self::new(ExprInner::FieldAssign(FieldAssign { name, path, value }))
     }
 
     pub fn new_variable_definition<S: ToString>(name: S, value: Expr) -> Self {
         let name = name.to_string();
-        Self(Box::new(ExprInner::VarDef(VarDef { name, value })))
+        Self::new(ExprInner::VarDef(VarDef { name, value }))
+    }
+
+    pub fn new_global<S: ToString>(name: S, value: Expr) -> Self {
+        let name = name.to_string();
+        Self::new(ExprInner::Global(VarDef { name, value }))
     }
 
     pub fn new_variable_assignment<S: ToString>(name: S, value: Expr) -> Self {
         let name = name.to_string();
-        Self(Box::new(ExprInner::VarAssign(VarAssign { name, value })))
+        Self::new(ExprInner::VarAssign(VarAssign { name, value }))
     }
 
     pub fn new_variable_call<S: ToString>(name: S) -> Self {
         let name = name.to_string();
-        Self(Box::new(ExprInner::VarCall(VarCall { name })))
+        Self::new(ExprInner::VarCall(VarCall { name }))
     }
 
     pub fn new_function_definition<S: ToString>(parameter_names: Vec<S>, body: Scope) -> Self {
         let parameter_names = parameter_names.into_iter().map(|s| s.to_string()).collect();
-        Self(Box::new(ExprInner::FnDef(FnDef {
+        Self::new(ExprInner::FnDef(FnDef {
             body,
             parameter_names,
-        })))
+        }))
+    }
+
+    pub fn new_call(callee: Expr, arguments: Vec<Expr>) -> Self {
+        Self::new(ExprInner::FnCall(FnCall { callee, arguments }))
     }
 
     pub fn new_function_call<S: ToString>(name: S, arguments: Vec<Expr>) -> Self {
-        let name = name.to_string();
-        Self(Box::new(ExprInner::FnCall(FnCall { name, arguments })))
+        Self::new_call(Self::new_variable_call(name), arguments)
     }
 
     pub fn new_function_return(value: Expr) -> Self {
-        Self(Box::new(ExprInner::FnRet(FnRet { value })))
+        Self::new(ExprInner::FnRet(FnRet { value }))
     }
 
     pub fn new_loop(body: Scope) -> Self {
-        Self(Box::new(ExprInner::Loop(Loop { body })))
+        Self::new(ExprInner::Loop(Loop { body }))
     }
 
     pub fn new_loop_break(value: Expr) -> Self {
-        Self(Box::new(ExprInner::LoopBr(LoopBr { value })))
+        Self::new(ExprInner::LoopBr(LoopBr { value }))
+    }
+
+    pub fn new_import<S: ToString>(path: S) -> Self {
+        let path = path.to_string();
+        Self::new(ExprInner::Import(Import { path }))
+    }
+
+    pub fn new_test<S: ToString>(name: S, body: Scope) -> Self {
+        let name = name.to_string();
+        Self::new(ExprInner::Test(Test { name, body }))
     }
 
     pub fn new_condition(condition: Expr, arm_true: Expr, arm_false: Option<Expr>) -> Self {
-        Self(Box::new(ExprInner::Cond(Cond {
+        Self::new(ExprInner::Cond(Cond {
             condition,
             arm_true,
             arm_false,
-        })))
+        }))
     }
 }
 
@@ -92,6 +158,30 @@ impl From<Literal> for Expr {
     }
 }
 
+impl From<List> for Expr {
+    fn from(input: List) -> Self {
+        Self::new(ExprInner::List(input))
+    }
+}
+
+impl From<Object> for Expr {
+    fn from(input: Object) -> Self {
+        Self::new(ExprInner::Object(input))
+    }
+}
+
+impl From<FieldCall> for Expr {
+    fn from(input: FieldCall) -> Self {
+        Self::new(ExprInner::FieldCall(input))
+    }
+}
+
+impl From<FieldAssign> for Expr {
+    fn from(input: FieldAssign) -> Self {
+        Self::new(ExprInner::FieldAssign(input))
+    }
+}
+
 impl From<VarDef> for Expr {
     fn from(input: VarDef) -> Self {
         Self::new(ExprInner::VarDef(input))
@@ -146,6 +236,18 @@ impl From<Cond> for Expr {
     }
 }
 
+impl From<Import> for Expr {
+    fn from(input: Import) -> Self {
+        Self::new(ExprInner::Import(input))
+    }
+}
+
+impl From<Test> for Expr {
+    fn from(input: Test) -> Self {
+        Self::new(ExprInner::Test(input))
+    }
+}
+
 impl<T> From<T> for Expr
 where
     T: Into<Value>,
@@ -159,6 +261,10 @@ where
 pub enum ExprInner {
     Scope(Scope),
     Literal(Literal),
+    List(List),
+    Object(Object),
+    FieldCall(FieldCall),
+    FieldAssign(FieldAssign),
     VarDef(VarDef),
     VarAssign(VarAssign),
     VarCall(VarCall),
@@ -168,6 +274,10 @@ pub enum ExprInner {
     Loop(Loop),
     LoopBr(LoopBr),
     Cond(Cond),
+    Import(Import),
+    Test(Test),
+    /// Definition of a prelude value or a linked module, the only names modules see.
+    Global(VarDef),
 }
 
 #[derive(Debug)]
@@ -184,6 +294,30 @@ impl Scope {
 #[derive(Debug)]
 pub struct Literal(pub Value);
 
+#[derive(Debug)]
+pub struct List {
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Debug)]
+pub struct Object {
+    pub fields: Vec<(String, Expr)>,
+}
+
+#[derive(Debug)]
+pub struct FieldCall {
+    pub object: Expr,
+    pub field: String,
+}
+
+/// Assignment to `name.path[0].path[1]...`, the path being never empty.
+#[derive(Debug)]
+pub struct FieldAssign {
+    pub name: String,
+    pub path: Vec<String>,
+    pub value: Expr,
+}
+
 #[derive(Debug)]
 pub struct VarDef {
     pub name: String,
@@ -209,10 +343,20 @@ pub struct FnDef {
 
 #[derive(Debug)]
 pub struct FnCall {
-    pub name: String,
+    pub callee: Expr,
     pub arguments: Vec<Expr>,
 }
 
+impl FnCall {
+    /// The name of the called variable, if the callee is one.
+    pub fn callee_name(&self) -> Option<&str> {
+        match self.callee.inner() {
+            ExprInner::VarCall(VarCall { name }) => Some(name),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct FnRet {
     pub value: Expr,
@@ -235,4 +379,17 @@ pub struct Cond {
     pub arm_false: Option<Expr>,
 }
 
+/// `import "path"`, replaced by the object of the module's definitions before resolution.
+#[derive(Debug)]
+pub struct Import {
+    pub path: String,
+}
+
+/// `test "name" { ... }`, skipped unless that test is selected by `porte test`.
+#[derive(Debug)]
+pub struct Test {
+    pub name: String,
+    pub body: Scope,
+}
+
 pub mod parser;
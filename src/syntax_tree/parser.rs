@@ -1,25 +1,53 @@
+use std::rc::Rc;
+
 use super::*;
-use chumsky::{prelude::*, text::whitespace};
+use crate::{
+    error::Error,
+    source::{Source, Sources},
+};
+use chumsky::{prelude::*, text::whitespace, Stream};
 
 pub trait AbstractParser<T>: Parser<char, T, Error = Simple<char>> {}
 
 impl<T, U: Parser<char, T, Error = Simple<char>>> AbstractParser<T> for U {}
 
+/// Whitespace and comments, which are never significant.
+pub fn blank() -> impl AbstractParser<()> + Clone {
+    let line_comment = just("//").then(none_of('\n').repeated()).ignored();
+    let block_comment = just("/*").then(take_until(just("*/"))).ignored();
+    let comment = line_comment.or(block_comment);
+    whitespace()
+        .then(comment.then(whitespace()).repeated())
+        .ignored()
+}
+
+#[test]
+fn test_blank() {
+    let parser = blank().then(just('a')).then_ignore(end());
+    assert!(parser.parse(" // line\n\t/* block\n */ /**/a").is_ok());
+    assert!(parser.parse("/* unclosed a").is_err());
+}
+
 pub fn debugging_expression_parser() -> impl AbstractParser<Expr> + Clone {
-    variable_call_parser().padded().map(|v| v.into())
+    variable_call_parser().padded_by(blank()).map(|v| v.into())
 }
 
-pub fn scope_parser(expression: impl AbstractParser<Expr> + Clone) -> impl AbstractParser<Scope> {
+pub fn scope_parser(
+    expression: impl AbstractParser<Expr> + Clone,
+) -> impl AbstractParser<Scope> + Clone {
     let open_list = expression.separated_by(just(';'));
-    let closed_list = open_list.clone().then_ignore(just(';').then(whitespace()));
+    let closed_list = open_list.clone().then_ignore(just(';').then(blank()));
     open_list
         .map(|instructions| Scope { instructions })
         .or(closed_list.map(|mut instructions| {
             instructions.push(Value::None.into());
             Scope { instructions }
         }))
-        .padded()
+        .padded_by(blank())
         .delimited_by(just('{'), just('}'))
+        .recover_with(nested_delimiters('{', '}', [('(', ')')], |_| Scope {
+            instructions: Vec::new(),
+        }))
 }
 
 #[test]
@@ -29,28 +57,37 @@ fn test_scope_parser() {
     dbg!(value.unwrap());
 }
 
-// TODO: add objects ?
-pub fn literal_value() -> impl AbstractParser<Value> {
+pub fn literal_value() -> impl AbstractParser<Value> + Clone {
+    recursive(|literal_value| {
+        let list = list_parser(literal_value).map(Value::List);
+        scalar_literal_value().or(list)
+    })
+}
+
+fn scalar_literal_value() -> impl AbstractParser<Value> + Clone {
     let bool = just("false")
         .map(|_| false.into())
         .or(just("true").map(|_| true.into()));
 
     let none = just("none").map(|_| Value::None);
 
-    let string = just('\"')
-        .ignore_then(none_of("\"").repeated())
-        .then_ignore(just('\"'))
-        .map(|v| String::from_iter(&v).into());
+    let string = plain_string().map(Value::from);
 
     let frac = just('.').chain(text::digits(10));
+    // `3` is an integer, `3.0` a float
     let number = just('-')
         .or_not()
         .chain(text::int(10))
         .chain::<char, _, _>(frac.or_not().flatten())
         .collect::<String>()
-        .from_str()
-        .unwrapped()
-        .map(|n: f64| n.into());
+        .try_map(|number, span| match number.parse::<i64>() {
+            Ok(int) => Ok(int.into()),
+            Err(_) if number.contains('.') => Ok(number.parse::<f64>().unwrap().into()),
+            Err(_) => Err(Simple::custom(
+                span,
+                format!("integer '{number}' is out of range"),
+            )),
+        });
 
     bool.or(none).or(string).or(number)
 }
@@ -59,24 +96,140 @@ pub fn literal_value() -> impl AbstractParser<Value> {
 fn test_literal_value() {
     let parser = literal_value();
     let value = parser.parse("5");
-    assert_eq!(value.unwrap().as_number().unwrap(), 5.);
+    assert_eq!(value.unwrap().as_int().unwrap(), 5);
+    assert_eq!(parser.parse("-5.0").unwrap().as_int(), None);
+    assert!(parser.parse("9223372036854775808").is_err());
+    let value = parser.parse(r#"[1, [ "a" ], ]"#).unwrap();
+    assert_eq!(value.as_list().unwrap().len(), 2);
+    let value = parser.parse(r#""say \"hi\"\n\t\\ \u{e9}\$""#);
+    assert_eq!(value.unwrap().as_string().unwrap(), "say \"hi\"\n\t\\ é$");
+    assert!(parser.parse(r#""${a}""#).is_err());
+}
+
+/// A string literal without interpolation.
+fn plain_string() -> impl AbstractParser<String> + Clone {
+    just('"')
+        .ignore_then(string_character().repeated())
+        .then_ignore(just('"'))
+        .collect()
+}
+
+/// Comma separated items between brackets, a trailing comma being allowed.
+pub fn list_parser<T>(item: impl AbstractParser<T> + Clone) -> impl AbstractParser<Vec<T>> + Clone {
+    item.separated_by(just(',').padded_by(blank()))
+        .allow_trailing()
+        .padded_by(blank())
+        .delimited_by(just('['), just(']'))
+}
+
+/// A character of a string literal, either verbatim or escaped with a backslash.
+/// `${` opens an interpolation and is rejected here.
+fn string_character() -> impl AbstractParser<char> + Clone {
+    let unicode = filter(|c: &char| c.is_ascii_hexdigit())
+        .repeated()
+        .at_least(1)
+        .at_most(6)
+        .collect::<String>()
+        .delimited_by(just("u{"), just('}'))
+        .try_map(|digits, span| {
+            u32::from_str_radix(&digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| Simple::custom(span, format!("invalid unicode escape '{digits}'")))
+        });
+    let escape = just('\\').ignore_then(
+        one_of("\"\\$")
+            .or(just('n').to('\n'))
+            .or(just('t').to('\t'))
+            .or(unicode),
+    );
+    let dollar = just('$').then_ignore(none_of('{').rewind());
+    none_of("\"\\$").or(escape).or(dollar)
+}
+
+/// A string literal containing `${expression}` interpolations,
+/// desugared into the concatenation of its parts, converted with `str`.
+pub fn interpolated_string_parser(
+    expression: impl AbstractParser<Expr> + Clone,
+) -> impl AbstractParser<Expr> + Clone {
+    let text = string_character()
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .map(Expr::new_literal);
+    let interpolation = expression
+        .delimited_by(just("${"), just('}'))
+        .map_with_span(|expression, span| desugared_call("str", vec![expression], span));
+    text.or(interpolation)
+        .repeated()
+        .delimited_by(just('"'), just('"'))
+        .map_with_span(|parts, span: Span| {
+            parts
+                .into_iter()
+                .fold(Expr::new_literal(""), |string, part| {
+                    desugared_call("add", vec![string, part], span.clone())
+                })
+        })
 }
 
+#[test]
+fn test_interpolated_string_parser() {
+    let parser = interpolated_string_parser(debugging_expression_parser());
+    let value = parser.parse(r#""backup of ${ name } done""#).unwrap();
+    let ExprInner::FnCall(call) = value.inner() else {
+        panic!("expected a function call");
+    };
+    assert_eq!(call.callee_name(), Some("add"));
+    assert!(matches!(call.arguments[1].inner(), ExprInner::Literal(_)));
+}
+
+#[test]
+fn test_interpolated_string_evaluation() {
+    use crate::engine::eval;
+    let value = eval(r#"name: "db"; "backup of ${name} done in ${1 + 2}s""#);
+    assert_eq!(value.as_string().unwrap(), "backup of db done in 3s");
+    let value = eval(r#""${ "\"${1}\"" }\t\u{1F600}""#);
+    assert_eq!(value.as_string().unwrap(), "\"1\"\t😀");
+}
+
+const KEYWORDS: [&str; 10] = [
+    "if", "else", "loop", "break", "return", "true", "false", "none", "import", "test",
+];
+
 pub fn name() -> impl AbstractParser<String> + Clone {
     let first = one_of("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ");
-    let rest = first.clone().or(one_of("1234567890-_+/*"));
+    let rest = first.clone().or(one_of("1234567890_"));
 
-    first.then(rest.repeated()).map(|(f, v)| {
-        let rest = String::from_iter(&v);
-        format!("{f}{rest}")
-    })
+    first
+        .then(rest.repeated())
+        .map(|(f, v)| {
+            let rest = String::from_iter(&v);
+            format!("{f}{rest}")
+        })
+        .try_map(|name, span| {
+            if KEYWORDS.contains(&name.as_str()) {
+                Err(Simple::custom(span, format!("'{name}' is a keyword")))
+            } else {
+                Ok(name)
+            }
+        })
+}
+
+#[test]
+fn test_name() {
+    let parser = name();
+    assert_eq!(parser.parse("say_it2").unwrap(), "say_it2");
+    assert_eq!(parser.parse("n-1").unwrap(), "n");
+    assert_eq!(parser.parse("a*b").unwrap(), "a");
+    assert!(parser.parse("loop").is_err());
+    assert_eq!(parser.then_ignore(blank()).parse("a// b").unwrap(), "a");
 }
 
 pub fn variable_definition_parser(
     expression: impl AbstractParser<Expr>,
 ) -> impl AbstractParser<VarDef> {
     name()
-        .then_ignore(just(":").padded())
+        .then_ignore(just(":").padded_by(blank()))
         .then(expression)
         .map(|(name, value)| VarDef { name, value })
 }
@@ -92,7 +245,7 @@ pub fn variable_assignement_parser(
     expression: impl AbstractParser<Expr>,
 ) -> impl AbstractParser<VarAssign> {
     name()
-        .then_ignore(just("<-").padded())
+        .then_ignore(just("<-").padded_by(blank()))
         .then(expression)
         .map(|(name, value)| VarAssign { name, value })
 }
@@ -104,10 +257,42 @@ fn test_variable_assignement_parser() {
     dbg!(value.unwrap());
 }
 
+/// Assignment to a field of an object held by a variable, possibly nested: `a.b.c <- 1`.
+pub fn field_assignment_parser(
+    expression: impl AbstractParser<Expr>,
+) -> impl AbstractParser<FieldAssign> {
+    let field = blank().then(just('.')).then(blank()).ignore_then(name());
+    name()
+        .then(field.repeated().at_least(1))
+        .then_ignore(just("<-").padded_by(blank()))
+        .then(expression)
+        .map(|((name, path), value)| FieldAssign { name, path, value })
+}
+
+#[test]
+fn test_field_assignment_parser() {
+    let parser = field_assignment_parser(debugging_expression_parser());
+    let value = parser.parse("a.b .c <- d").unwrap();
+    assert_eq!(value.path, vec!["b", "c"]);
+    assert!(parser.parse("a <- d").is_err());
+}
+
 pub fn variable_call_parser() -> impl AbstractParser<VarCall> + Clone {
     name().map(|name| VarCall { name })
 }
 
+/// Rejects names that other parsers would extend, so that a recovered definition or assignment is
+/// not shadowed by a shorter variable call.
+fn standalone_variable_call_parser() -> impl AbstractParser<VarCall> + Clone {
+    let suffix = blank().ignore_then(just(':').ignored().or(just("<-").ignored()));
+    variable_call_parser().then_ignore(suffix.or_not().rewind().try_map(
+        |suffix, span| match suffix {
+            Some(()) => Err(Simple::custom(span, "incomplete expression")),
+            None => Ok(()),
+        },
+    ))
+}
+
 #[test]
 fn test_variable_call_parser() {
     let parser = variable_call_parser();
@@ -118,12 +303,12 @@ fn test_variable_call_parser() {
 pub fn function_definition_parser(
     expression: impl AbstractParser<Expr> + Clone,
 ) -> impl AbstractParser<FnDef> {
-    let parameters = name().separated_by(just(',').padded());
+    let parameters = name().separated_by(just(',').padded_by(blank()));
     let body = scope_parser(expression);
     parameters
-        .padded()
+        .padded_by(blank())
         .delimited_by(just('('), just(')'))
-        .then_ignore(just("=>").padded())
+        .then_ignore(just("=>").padded_by(blank()))
         .then(body)
         .map(|(parameter_names, body)| FnDef {
             body,
@@ -138,23 +323,108 @@ fn test_function_definition_parser() {
     dbg!(value.unwrap());
 }
 
-pub fn function_call_parser(expression: impl AbstractParser<Expr>) -> impl AbstractParser<FnCall> {
-    let parameters = expression.separated_by(just(',').padded());
-    name()
-        .then(
-            parameters
-                .padded()
-                .delimited_by(just('('), just(')'))
-                .padded(),
-        )
-        .map(|(name, arguments)| FnCall { arguments, name })
+enum Postfix {
+    Call(Vec<Expr>),
+    Field(String),
+}
+
+/// A primary expression followed by any number of argument lists and field reads,
+/// `f(a).b(c)` calling the field `b` of the result of `f(a)`. Field reads may start on another
+/// line, argument lists start right after what they call.
+pub fn postfix_parser(
+    primary: impl AbstractParser<Expr> + Clone,
+    expression: impl AbstractParser<Expr> + Clone,
+) -> impl AbstractParser<Expr> + Clone {
+    let arguments = expression
+        .separated_by(just(',').padded_by(blank()))
+        .padded_by(blank())
+        .delimited_by(just('('), just(')'))
+        .recover_with(nested_delimiters('(', ')', [('{', '}')], |_| Vec::new()))
+        .map(Postfix::Call);
+    let field = just('.')
+        .then(blank())
+        .ignore_then(name())
+        .map(Postfix::Field);
+    // a call follows its callee directly, a parenthesized expression after a space is not one
+    let postfix = arguments
+        .or(blank().ignore_then(field))
+        .map_with_span(|postfix, span: Span| (postfix, span));
+    primary
+        .then(postfix.repeated())
+        .foldl(|expression, (postfix, span)| {
+            let span = expression.span().start..span.end;
+            let expression = match postfix {
+                Postfix::Call(arguments) => Expr::new_call(expression, arguments),
+                Postfix::Field(field) => Expr::new_field_call(expression, field),
+            };
+            expression.with_span(span)
+        })
 }
 
 #[test]
-fn test_function_call_parser() {
-    let parser = function_call_parser(debugging_expression_parser());
-    let value = parser.parse("f( a , b )");
-    dbg!(value.unwrap());
+fn test_postfix_parser() {
+    let primary = debugging_expression_parser().map_with_span(|e: Expr, span| e.with_span(span));
+    let parser = postfix_parser(primary, debugging_expression_parser());
+    let value = parser.parse("f( a , b ).c(d)").unwrap();
+    assert_eq!(value.span(), &(0..15));
+    let ExprInner::FnCall(outer) = value.into_inner() else {
+        panic!("expected a function call");
+    };
+    let ExprInner::FieldCall(FieldCall { object, field }) = outer.callee.inner() else {
+        panic!("expected a field read");
+    };
+    assert_eq!(field, "c");
+    let ExprInner::FnCall(inner) = object.inner() else {
+        panic!("expected a chained call");
+    };
+    assert_eq!(inner.callee_name(), Some("f"));
+    assert_eq!(inner.arguments.len(), 2);
+    let value = parser.parse("f\n  .c").unwrap();
+    assert!(matches!(value.inner(), ExprInner::FieldCall(_)));
+}
+
+#[test]
+fn test_parenthesized_condition_arms() {
+    let value = expression_parser().parse("if x (1) else (2)").unwrap();
+    let ExprInner::Cond(Cond {
+        condition,
+        arm_true,
+        arm_false,
+    }) = value.inner()
+    else {
+        panic!("expected a condition");
+    };
+    assert!(matches!(condition.inner(), ExprInner::VarCall(_)));
+    assert!(matches!(arm_true.inner(), ExprInner::Literal(_)));
+    assert!(matches!(
+        arm_false.as_ref().unwrap().inner(),
+        ExprInner::Literal(_)
+    ));
+}
+
+/// `{ name: value, ... }`, told apart from a scope by having fields separated by commas.
+pub fn object_parser(
+    expression: impl AbstractParser<Expr> + Clone,
+) -> impl AbstractParser<Object> + Clone {
+    let field = name()
+        .then_ignore(just(':').padded_by(blank()))
+        .then(expression);
+    field
+        .separated_by(just(',').padded_by(blank()))
+        .at_least(1)
+        .allow_trailing()
+        .padded_by(blank())
+        .delimited_by(just('{'), just('}'))
+        .map(|fields| Object { fields })
+}
+
+#[test]
+fn test_object_parser() {
+    let parser = object_parser(debugging_expression_parser());
+    let value = parser.parse("{ a: b, c : d, }").unwrap();
+    assert_eq!(value.fields.len(), 2);
+    assert!(parser.parse("{ a: b; c }").is_err());
+    assert!(parser.parse("{}").is_err());
 }
 
 pub fn function_return_parser(expression: impl AbstractParser<Expr>) -> impl AbstractParser<FnRet> {
@@ -173,7 +443,7 @@ fn test_function_return_parser() {
 
 pub fn loop_parser(expression: impl AbstractParser<Expr> + Clone) -> impl AbstractParser<Loop> {
     just("loop")
-        .then(whitespace())
+        .then(blank())
         .ignore_then(scope_parser(expression))
         .map(|body| Loop { body })
 }
@@ -187,7 +457,7 @@ fn test_loop_parser() {
 
 pub fn loop_break_parser(expression: impl AbstractParser<Expr>) -> impl AbstractParser<LoopBr> {
     just("break")
-        .ignore_then(just(" ").then(whitespace()).ignore_then(expression))
+        .ignore_then(just(" ").then(blank()).ignore_then(expression))
         .map(|value| LoopBr { value })
 }
 
@@ -198,6 +468,41 @@ fn test_loop_break_parser() {
     dbg!(value.unwrap());
 }
 
+pub fn import_parser() -> impl AbstractParser<Import> + Clone {
+    just("import")
+        .then(just(' ').then(blank()))
+        .ignore_then(plain_string())
+        .map(|path| Import { path })
+}
+
+#[test]
+fn test_import_parser() {
+    let parser = import_parser();
+    let value = parser.parse(r#"import  "lib/array.pr""#).unwrap();
+    assert_eq!(value.path, "lib/array.pr");
+    assert!(parser.parse(r#"import "${name}.pr""#).is_err());
+}
+
+pub fn test_declaration_parser(
+    expression: impl AbstractParser<Expr> + Clone,
+) -> impl AbstractParser<Test> {
+    just("test")
+        .then(just(' ').then(blank()))
+        .ignore_then(plain_string())
+        .then_ignore(blank())
+        .then(scope_parser(expression))
+        .map(|(name, body)| Test { name, body })
+}
+
+#[test]
+fn test_test_declaration_parser() {
+    let parser = test_declaration_parser(debugging_expression_parser());
+    let value = parser.parse(r#"test "sums  add" { a; b }"#).unwrap();
+    assert_eq!(value.name, "sums  add");
+    assert_eq!(value.body.instructions.len(), 2);
+    assert!(parser.parse(r#"test "${name}" {}"#).is_err());
+}
+
 pub fn condition_parser(
     expression: impl AbstractParser<Expr> + Clone,
 ) -> impl AbstractParser<Cond> {
@@ -219,97 +524,129 @@ fn test_condition_parser() {
     dbg!(value.unwrap());
 }
 
-fn _sugar_parser(expression: impl AbstractParser<Expr> + Clone) -> impl AbstractParser<Expr> {
-    let add = expression
-        .clone()
-        .then_ignore(just(" + "))
-        .then(expression.clone())
-        .map(|(l, r)| {
-            FnCall {
-                name: "add".into(),
-                arguments: vec![l, r],
-            }
-            .into()
-        });
+/// A call to a prelude function, spanning the code it desugars.
+fn desugared_call(name: &str, arguments: Vec<Expr>, span: Span) -> Expr {
+    let callee = Expr::new_variable_call(name).with_span(span.clone());
+    Expr::new_call(callee, arguments).with_span(span)
+}
 
-    let sub = expression
-        .clone()
-        .then_ignore(just(" - "))
-        .then(expression.clone())
-        .map(|(l, r)| {
-            FnCall {
-                name: "sub".into(),
-                arguments: vec![l, r],
-            }
-            .into()
-        });
+/// Desugars a binary operator into a call to the prelude function implementing it.
+fn binary_operation(name: &'static str) -> impl Fn(Expr, Expr) -> Expr + Clone {
+    move |lhs, rhs| {
+        let span = lhs.span().start..rhs.span().end;
+        desugared_call(name, vec![lhs, rhs], span)
+    }
+}
 
-    let eq = expression
-        .clone()
-        .then_ignore(just(" == "))
-        .then(expression.clone())
-        .map(|(l, r)| {
-            FnCall {
-                name: "eq".into(),
-                arguments: vec![l, r],
-            }
-            .into()
-        });
+/// Desugars a comparison operator into the negation of its complement.
+fn negated_operation(name: &'static str) -> impl Fn(Expr, Expr) -> Expr + Clone {
+    move |lhs, rhs| {
+        let operation = binary_operation(name)(lhs, rhs);
+        let span = operation.span().clone();
+        desugared_call("not", vec![operation], span)
+    }
+}
 
-    let sup = expression
-        .clone()
-        .then_ignore(just(" > "))
-        .then(expression.clone())
-        .map(|(l, r)| {
-            FnCall {
-                name: "sup".into(),
-                arguments: vec![l, r],
-            }
-            .into()
-        });
+type Operation = Rc<dyn Fn(Expr, Expr) -> Expr>;
 
-    let inf = expression
-        .clone()
-        .then_ignore(just(" < "))
-        .then(expression.clone())
-        .map(|(l, r)| {
-            FnCall {
-                name: "inf".into(),
-                arguments: vec![l, r],
-            }
-            .into()
-        });
+fn operator<F>(symbol: &'static str, operation: F) -> impl AbstractParser<Operation> + Clone
+where
+    F: Fn(Expr, Expr) -> Expr + 'static,
+{
+    let operation: Operation = Rc::new(operation);
+    just(symbol).to(operation)
+}
 
-    let or = expression
+/// Left-associative chain of operands separated by operators of the same precedence.
+fn binary_level(
+    operand: impl AbstractParser<Expr> + Clone,
+    operators: impl AbstractParser<Operation> + Clone,
+) -> impl AbstractParser<Expr> + Clone {
+    operand
         .clone()
-        .then_ignore(just(" || "))
-        .then(expression.clone())
-        .map(|(l, r)| {
-            FnCall {
-                name: "or".into(),
-                arguments: vec![l, r],
-            }
-            .into()
-        });
+        .then(operators.padded_by(blank()).then(operand).repeated())
+        .foldl(|lhs, (operation, rhs)| operation(lhs, rhs))
+}
+
+/// Infix and prefix operators, from the loosest `||` to the tightest unary `-` and `!`.
+pub fn operation_parser(
+    operand: impl AbstractParser<Expr> + Clone + 'static,
+) -> impl AbstractParser<Expr> + Clone {
+    let unary = recursive(|unary| {
+        let negation = just('-')
+            .ignore_then(unary.clone())
+            .map_with_span(|value: Expr, span| {
+                desugared_call("sub", vec![0i64.into(), value], span)
+            });
+        let complement = just('!')
+            .ignore_then(unary)
+            .map_with_span(|value: Expr, span| desugared_call("not", vec![value], span));
+        operand.or(negation).or(complement).padded_by(blank())
+    });
 
-    let and = expression
-        .clone()
-        .then_ignore(just(" && "))
-        .then(expression)
-        .map(|(l, r)| {
-            FnCall {
-                name: "and".into(),
-                arguments: vec![l, r],
-            }
-            .into()
-        });
+    let product = binary_level(
+        unary,
+        operator("*", binary_operation("mul"))
+            .or(operator("/", binary_operation("div")))
+            .or(operator("%", binary_operation("mod"))),
+    );
+    let sum = binary_level(
+        product,
+        operator("+", binary_operation("add")).or(operator("-", binary_operation("sub"))),
+    );
+    // `<` directly followed by `-` is an assignment
+    let inferior = operator("<", binary_operation("inf")).then_ignore(none_of('-').rewind());
+    let comparison = binary_level(
+        sum,
+        operator("<=", negated_operation("sup"))
+            .or(operator(">=", negated_operation("inf")))
+            .or(inferior)
+            .or(operator(">", binary_operation("sup"))),
+    );
+    let equality = binary_level(
+        comparison,
+        operator("==", binary_operation("eq")).or(operator("!=", negated_operation("eq"))),
+    );
+    let conjunction = binary_level(equality, operator("&&", binary_operation("and")));
+    binary_level(conjunction, operator("||", binary_operation("or")))
+}
 
-    eq.or(add).or(sub).or(sup).or(inf).or(or).or(and)
+#[test]
+fn test_operation_parser() {
+    let parser = expression_parser().then_ignore(end());
+    let value = parser.parse("a || b && c == d + e * f").unwrap();
+    let ExprInner::FnCall(call) = value.inner() else {
+        panic!("expected a function call");
+    };
+    assert_eq!(call.callee_name(), Some("or"));
+    assert_eq!(call.arguments[1].span(), &(5..24));
+    assert!(parser.parse("a <= -b").is_ok());
+    // names do not contain `-`, so unspaced subtractions are still operations
+    for text in ["a-1", "a-b", "len(l)-1"] {
+        let value = parser.parse(text).unwrap();
+        let ExprInner::FnCall(call) = value.inner() else {
+            panic!("expected a function call for '{text}'");
+        };
+        assert_eq!(call.callee_name(), Some("sub"), "{text}");
+    }
+    let value = parser.parse("a <- b").unwrap();
+    assert!(matches!(value.inner(), ExprInner::VarAssign(_)));
+}
+
+#[test]
+fn test_operation_evaluation() {
+    use crate::engine::eval;
+    assert_eq!(eval("1 + 2 * 3 - 4 / 2").as_number().unwrap(), 5.);
+    assert_eq!(eval("(1 + 2) * -3 % 4").as_number().unwrap(), -1.);
+    assert!(eval("1 + 1 == 2 && !(3 <= 2) || false").as_bool().unwrap());
+    assert!(eval("a: 2; a >= 2 && a != 3 && a > 1 && a < 3")
+        .as_bool()
+        .unwrap());
+    assert_eq!(eval("a: 1; a <- a + 1; a").as_number().unwrap(), 2.);
 }
 
 pub fn expression_parser() -> impl AbstractParser<Expr> {
     let expression = recursive(|expression| {
-        //let sugar = sugar_parser(expression.clone());
         let condition = condition_parser(expression.clone()).map(|i| i.into());
         let function_definition = function_definition_parser(expression.clone()).map(|i| i.into());
         let function_return = function_return_parser(expression.clone()).map(|i| i.into());
@@ -317,29 +654,63 @@ pub fn expression_parser() -> impl AbstractParser<Expr> {
         let loop_break = loop_break_parser(expression.clone()).map(|i| i.into());
         let scope = scope_parser(expression.clone()).map(|i| i.into());
         let litteral = literal_value().map(Expr::new_literal);
+        let interpolated_string = interpolated_string_parser(expression.clone());
+        let list = list_parser(expression.clone())
+            .recover_with(nested_delimiters(
+                '[',
+                ']',
+                [('(', ')'), ('{', '}')],
+                |_| Vec::new(),
+            ))
+            .map(Expr::new_list);
         let variable_definition = variable_definition_parser(expression.clone()).map(|i| i.into());
         let variable_assignment = variable_assignement_parser(expression.clone()).map(|i| i.into());
-        let function_call = function_call_parser(expression.clone()).map(|i| i.into());
-        let variable_call = variable_call_parser().map(|i| i.into());
+        let field_assignment = field_assignment_parser(expression.clone()).map(|i| i.into());
+        let object = object_parser(expression.clone()).map(|i| i.into());
+        let variable_call = standalone_variable_call_parser().map(|i| i.into());
+        let group = expression.clone().delimited_by(just('('), just(')'));
+        let import = import_parser().map(|i| i.into());
+        let test = test_declaration_parser(expression.clone()).map(|i| i.into());
+
+        let primary = object
+            .or(import)
+            .or(scope)
+            .or(litteral)
+            .or(interpolated_string)
+            .or(list)
+            .or(variable_call)
+            .or(group)
+            .map_with_span(|expression: Expr, span| expression.with_span(span));
+        let operand = postfix_parser(primary, expression.clone());
+        let operation = operation_parser(operand);
 
-        //sugar
-        //    .or(condition)
         condition
+            .or(test)
             .or(function_definition)
             .or(function_return)
             .or(loop_)
             .or(loop_break)
-            .or(scope)
-            .or(litteral)
             .or(variable_definition)
             .or(variable_assignment)
-            .or(function_call)
-            .or(variable_call)
-            .padded()
+            .or(field_assignment)
+            .or(operation)
+            .map_with_span(|expression: Expr, span| expression.with_span(span))
+            .padded_by(blank())
     });
     expression
 }
 
+#[test]
+fn test_expression_span() {
+    let parser = expression_parser();
+    let value = parser.parse(" f(a, b)").unwrap();
+    assert_eq!(value.span(), &(1..8));
+    let ExprInner::FnCall(FnCall { arguments, .. }) = value.inner() else {
+        panic!("expected a function call");
+    };
+    assert_eq!(arguments[1].span(), &(6..7));
+}
+
 #[test]
 fn test_expression_parser() {
     let text = r##"if a b"##;
@@ -348,16 +719,25 @@ fn test_expression_parser() {
     dbg!(value.unwrap());
 }
 
-fn parser() -> impl AbstractParser<Program> {
-    let scope = expression_parser()
-        .separated_by(just(';').padded())
-        .then_ignore(just(';').padded().or_not())
+fn parser() -> impl AbstractParser<Scope> {
+    let terminator = just(';').ignored().or(end());
+    let statement = expression_parser()
+        .then_ignore(terminator.rewind())
+        .recover_with(skip_until([';'], |span| {
+            Expr::new_literal(Value::None).with_span(span)
+        }));
+    // recovering at the end of input would report a missing statement after a trailing `;`
+    let statement = blank().then(any().rewind()).ignore_then(statement);
+    // blank input, comments included, is an empty program
+    let scope = blank()
+        .ignore_then(
+            statement
+                .separated_by(just(';').padded_by(blank()))
+                .allow_trailing(),
+        )
         .then_ignore(end());
 
-    scope.map(|instructions| {
-        let body = Scope { instructions };
-        Program { body }
-    })
+    scope.map(|instructions| Scope { instructions })
 }
 
 #[test]
@@ -379,8 +759,63 @@ f(a)
     dbg!(e.unwrap());
 }
 
+#[test]
+fn test_parser_recovery() {
+    let example = r##"
+a: add(1 x);
+b: { c: ; 2 };
+d e;
+f: 3
+"##;
+    let parser = parser();
+    let (program, errors) = parser.parse_recovery(example);
+    assert!(program.is_some());
+    let lines: Vec<_> = errors.iter().map(|error| error.span().start).collect();
+    assert_eq!(lines, vec![10, 22, 31]);
+}
+
+#[test]
+fn test_comments() {
+    let example = r##"
+// leading comment
+a: 3; /* trailing comment */
+f: (x /* parameter */, // list
+    y) => {
+    // inside a scope
+    add(x, /* argument */ y) // before the closing brace
+};
+f(a, 4) // last statement
+"##;
+    let parser = parser();
+    let scope = parser.parse(example).unwrap();
+    assert_eq!(scope.instructions.len(), 3);
+}
+
+#[test]
+fn test_comment_only() {
+    let parser = parser();
+    for example in ["", " \n\t", "// only a comment", "/* block */\n// line\n"] {
+        let scope = parser.parse(example).unwrap();
+        assert!(scope.instructions.is_empty(), "{example:?}");
+    }
+    assert!(parser.parse("a; // trailing").is_ok());
+    let program = ParserWrapper::new().parse("// only a comment").unwrap();
+    assert!(program.body.instructions.is_empty());
+}
+
+#[test]
+fn test_examples() {
+    let parser = ParserWrapper::new();
+    parser
+        .parse(include_str!("../../examples/hello-world.pr"))
+        .unwrap();
+    parser
+        .parse(include_str!("../../examples/array.pr"))
+        .unwrap();
+}
+
 pub struct ParserWrapper {
-    inner: Box<dyn AbstractParser<Program>>,
+    inner: Box<dyn AbstractParser<Scope>>,
 }
 
 impl ParserWrapper {
@@ -389,8 +824,32 @@ impl ParserWrapper {
         ParserWrapper { inner }
     }
 
-    pub fn parse(&self, input: &str) -> Result<Program, Vec<Simple<char>>> {
-        self.inner.parse(input)
+    pub fn parse(&self, input: &str) -> Result<Program, Error> {
+        self.parse_source(Source::new("<input>", input))
+    }
+
+    /// Reports every error the parser could recover from, at most one per position.
+    pub fn parse_source(&self, source: Source) -> Result<Program, Error> {
+        // spans start at the offset of the source, keeping them distinct from other sources'
+        let offset = source.offset();
+        let characters = source.text().chars().enumerate();
+        let characters = characters.map(|(index, c)| (c, offset + index..offset + index + 1));
+        let end = source.end() - 1;
+        let (body, mut errors) = self
+            .inner
+            .parse_recovery(Stream::from_iter(end..end + 1, characters));
+        let source = Rc::new(source);
+        match body {
+            Some(body) if errors.is_empty() => {
+                let sources = Sources::new(source);
+                Ok(Program { body, sources })
+            }
+            _ => {
+                errors.sort_by_key(|error| error.span().start);
+                errors.dedup_by_key(|error| error.span().start);
+                Err(Error::Parse { source, errors })
+            }
+        }
     }
 }
 
@@ -403,7 +862,7 @@ impl Default for ParserWrapper {
 /*
 // example
 
-my-print: (input) => {
+my_print: (input) => {
     concatenated: {
         "now I would like to interject for a moment" + input
     };
@@ -0,0 +1,295 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    engine::Engine,
+    error::Error,
+    runtime::Outcome,
+    source::Source,
+    syntax_tree::{Expr, ExprInner, Program, Test},
+    value::Value,
+};
+
+/// Names of the tests declared at the top level of a program, in order.
+pub fn test_names(program: &Program) -> Result<Vec<String>, Error> {
+    let mut names: Vec<String> = Vec::new();
+    for instruction in &program.body.instructions {
+        if let ExprInner::Test(Test { name, .. }) = instruction.inner() {
+            if names.contains(name) {
+                let error = Error::new_resolve(format!("duplicate test '{name}'"));
+                return Err(error.at(program.sources.locate(instruction.span())));
+            }
+            names.push(name.clone());
+        }
+    }
+    Ok(names)
+}
+
+/// Keeps the top-level code of a program and runs the body of the named test after it, the
+/// other tests being dropped.
+pub fn select_test(program: Program, name: &str) -> Result<Program, Error> {
+    let Program { mut body, sources } = program;
+    let mut selected = None;
+    body.instructions.retain_mut(|instruction| {
+        let ExprInner::Test(test) = instruction.inner.as_mut() else {
+            return true;
+        };
+        if test.name == name && selected.is_none() {
+            let body = std::mem::take(&mut test.body.instructions);
+            selected = Some(Expr::new_scope(body).with_span(instruction.span.clone()));
+        }
+        false
+    });
+    let selected = selected.ok_or_else(|| Error::new_resolve(format!("no test '{name}'")))?;
+    body.instructions.push(selected);
+    Ok(Program { body, sources })
+}
+
+/// Counts of the tests run by `run_tests`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub filtered: usize,
+}
+
+struct Failure {
+    title: String,
+    error: Error,
+}
+
+/// Runs the tests of script files, and of the scripts in directories, whose names contain one
+/// of the filters, or every test without filters. Each test runs in a runtime of its own.
+pub fn run_tests<W: Write>(
+    engine: &Engine,
+    paths: &[PathBuf],
+    filters: &[String],
+    output: &mut W,
+) -> io::Result<Summary> {
+    let start = Instant::now();
+    let mut summary = Summary::default();
+    let mut failures = Vec::new();
+    let mut scripts = Vec::new();
+    for path in paths {
+        if let Err(error) = script_files(path, &mut scripts) {
+            let title = path.display().to_string();
+            failures.push(Failure {
+                title,
+                error: error.into(),
+            });
+            summary.failed += 1;
+        }
+    }
+
+    for path in scripts {
+        let title = path.display().to_string();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) => {
+                failures.push(Failure {
+                    title,
+                    error: error.into(),
+                });
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let names = match engine.tests(Source::new(&title, &text)) {
+            Ok(names) => names,
+            Err(error) => {
+                failures.push(Failure { title, error });
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let (names, filtered): (Vec<_>, Vec<_>) = names.into_iter().partition(|name| {
+            filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()))
+        });
+        summary.filtered += filtered.len();
+        if names.is_empty() {
+            continue;
+        }
+
+        writeln!(output, "running {} test(s) from {title}", names.len())?;
+        for name in names {
+            let source = Source::new(&title, &text);
+            let test_start = Instant::now();
+            // a test exiting cleanly passes, exiting with another status fails
+            let result = match engine.eval_test(source, &name) {
+                Ok(Outcome::Exit(code)) if code != 0 => {
+                    let message = format!("test exited with status {code}");
+                    Err(Error::new_assertion(message, None))
+                }
+                result => result,
+            };
+            let duration = format_duration(test_start.elapsed());
+            match result {
+                Ok(_) => {
+                    writeln!(output, "test {name} ... ok ({duration})")?;
+                    summary.passed += 1;
+                }
+                Err(error) => {
+                    writeln!(output, "test {name} ... FAILED ({duration})")?;
+                    let title = format!("{title}: {name}");
+                    failures.push(Failure { title, error });
+                    summary.failed += 1;
+                }
+            }
+        }
+        writeln!(output)?;
+    }
+
+    if !failures.is_empty() {
+        writeln!(output, "failures:")?;
+        for Failure { title, error } in &failures {
+            writeln!(output, "\n---- {title} ----\n{error}")?;
+            let values = match error.inner() {
+                Error::Assertion { values, .. } => values.as_deref(),
+                _ => None,
+            };
+            if let Some((Value::String(left), Value::String(right))) = values {
+                if left.contains('\n') || right.contains('\n') {
+                    writeln!(output, "diff (-left +right):")?;
+                    for line in diff_lines(left, right) {
+                        writeln!(output, "{line}")?;
+                    }
+                }
+            }
+        }
+        writeln!(output)?;
+    }
+    let Summary {
+        passed,
+        failed,
+        filtered,
+    } = summary;
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    let duration = format_duration(start.elapsed());
+    writeln!(
+        output,
+        "test result: {result}. {passed} passed; {failed} failed; {filtered} filtered out; finished in {duration}"
+    )?;
+    Ok(summary)
+}
+
+/// Collects the file, or the `.pr` files found in the directory, sorted by path.
+fn script_files(path: &Path, scripts: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        fs::metadata(path)?;
+        scripts.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|extension| extension == "pr") {
+            script_files(&entry, scripts)?;
+        }
+    }
+    Ok(())
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{duration:.2?}")
+}
+
+/// The lines of both texts, prefixed by `-` when only in the left one, `+` when only in the
+/// right one, and by spaces when in both.
+fn diff_lines(left: &str, right: &str) -> Vec<String> {
+    let left: Vec<_> = left.lines().collect();
+    let right: Vec<_> = right.lines().collect();
+    // common[i][j] is the length of the longest common subsequence of left[i..] and right[j..]
+    let mut common = vec![vec![0; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            common[i][j] = if left[i] == right[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            lines.push(format!("  {}", left[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if j == right.len() || (i < left.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("- {}", left[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", right[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[test]
+fn test_diff_lines() {
+    let lines = diff_lines("a\nb\nc", "a\nc\nd");
+    assert_eq!(lines, ["  a", "- b", "  c", "+ d"]);
+}
+
+#[test]
+fn test_run_tests() {
+    let root = std::env::temp_dir().join(format!("porte-tests-{}", std::process::id()));
+    fs::create_dir_all(root.join("nested")).unwrap();
+    let script = r#"
+double: (n) => { n * 2 };
+counter: obj();
+counter.count <- 0;
+test "double sums" {
+    counter.count <- counter.count + 1;
+    assert_eq(double(2), 4);
+    assert_eq(counter.count, 1)
+};
+test "double lines" { assert_eq("a\nb", "a\nc") };
+test "isolated" { assert(eq(counter.count, 0), "count leaked") };
+test "exits cleanly" { exit(0); assert(false, "ran after exit") };
+test "exits with failure" { exit(3) }
+"#;
+    fs::write(root.join("nested/double.pr"), script).unwrap();
+    fs::write(root.join("notes.txt"), "not a script").unwrap();
+
+    let engine = Engine::new();
+    let mut output = Vec::new();
+    let summary = run_tests(&engine, std::slice::from_ref(&root), &[], &mut output).unwrap();
+    let expected = Summary {
+        passed: 3,
+        failed: 2,
+        filtered: 0,
+    };
+    assert_eq!(summary, expected);
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("test double sums ... ok ("));
+    assert!(output.contains("test double lines ... FAILED ("));
+    assert!(output.contains("double.pr:10:23: assertion error: values differ"));
+    assert!(output.contains("diff (-left +right):\n  a\n- b\n+ c\n"));
+    assert!(output.contains("test exits cleanly ... ok ("));
+    assert!(output.contains("test exits with failure ... FAILED ("));
+    assert!(output.contains("test exited with status 3"));
+
+    let filters = ["sums".to_string()];
+    let summary = run_tests(
+        &engine,
+        std::slice::from_ref(&root),
+        &filters,
+        &mut io::sink(),
+    )
+    .unwrap();
+    fs::remove_dir_all(&root).unwrap();
+    let expected = Summary {
+        passed: 1,
+        failed: 0,
+        filtered: 4,
+    };
+    assert_eq!(summary, expected);
+}
@@ -1,26 +1,58 @@
-use std::collections::HashMap;
+use std::{
+    cell::{Ref, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
 
 use self::function::Function;
 pub mod function {
-    use crate::execution_tree::Id;
+    use std::fmt;
+
+    use crate::{
+        error::Error,
+        execution_tree::Id,
+        runtime::{Caller, Environment},
+    };
 
     use super::Value;
 
-    #[derive(Debug, Clone)]
+    #[derive(Clone)]
     pub struct ConstructedFunctionExecutor {
         pub parameter_ids: Vec<Id>,
         pub body_scope_id: Id,
+        /// The variables the function uses from where it was defined.
+        pub environment: Environment,
+        /// The variable bound to the function itself when it runs.
+        pub self_id: Option<Id>,
+    }
+
+    impl fmt::Debug for ConstructedFunctionExecutor {
+        /// Skips the environment, which may contain the function itself.
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("ConstructedFunctionExecutor")
+                .field("parameter_ids", &self.parameter_ids)
+                .field("body_scope_id", &self.body_scope_id)
+                .field("self_id", &self.self_id)
+                .finish_non_exhaustive()
+        }
     }
 
     #[derive(Debug, Clone)]
     pub struct NativeFunctionExecutor {
-        pub closure: fn(Vec<Value>) -> Value,
+        pub closure: fn(Vec<Value>) -> Result<Value, Error>,
+    }
+
+    /// A native function calling back porte functions, like `map`.
+    #[derive(Debug, Clone)]
+    pub struct HigherOrderFunctionExecutor {
+        pub closure: fn(Vec<Value>, &mut Caller) -> Result<Value, Error>,
     }
 
     #[derive(Debug, Clone)]
     pub enum FunctionExecutor {
         Constructed(ConstructedFunctionExecutor),
         Native(NativeFunctionExecutor),
+        HigherOrder(HigherOrderFunctionExecutor),
     }
 
     #[derive(Debug, Clone)]
@@ -30,11 +62,18 @@ pub mod function {
     }
 
     impl Function {
-        pub fn new_constructed(argument_ids: Vec<Id>, body_scope_id: Id) -> Self {
+        pub fn new_constructed(
+            argument_ids: Vec<Id>,
+            body_scope_id: Id,
+            environment: Environment,
+            self_id: Option<Id>,
+        ) -> Self {
             let argument_count = argument_ids.len();
             let executor = FunctionExecutor::Constructed(ConstructedFunctionExecutor {
                 parameter_ids: argument_ids,
                 body_scope_id,
+                environment,
+                self_id,
             });
             Self {
                 argument_count,
@@ -42,7 +81,10 @@ pub mod function {
             }
         }
 
-        pub fn new_native(argument_count: usize, closure: fn(Vec<Value>) -> Value) -> Self {
+        pub fn new_native(
+            argument_count: usize,
+            closure: fn(Vec<Value>) -> Result<Value, Error>,
+        ) -> Self {
             let executor = FunctionExecutor::Native(NativeFunctionExecutor { closure });
             Self {
                 argument_count,
@@ -50,6 +92,17 @@ pub mod function {
             }
         }
 
+        pub fn new_higher_order(
+            argument_count: usize,
+            closure: fn(Vec<Value>, &mut Caller) -> Result<Value, Error>,
+        ) -> Self {
+            let executor = FunctionExecutor::HigherOrder(HigherOrderFunctionExecutor { closure });
+            Self {
+                argument_count,
+                executor,
+            }
+        }
+
         pub fn executor(&self) -> &FunctionExecutor {
             &self.executor
         }
@@ -61,17 +114,39 @@ pub mod function {
         }
     }
 }
+/// Fields of an object, shared by every value referring to it.
+pub type ObjectRef = Rc<RefCell<HashMap<String, Value>>>;
+
+/// Objects have reference semantics: copying one aliases its fields, mutating them through a copy
+/// is visible through all others. Every other value is copied.
 #[derive(Debug, Clone)]
 pub enum Value {
     None,
     Bool(bool),
+    Int(i64),
     Number(f64),
     String(String),
-    Object(HashMap<String, Value>),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Object(ObjectRef),
     Function(Function),
 }
 
 impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Bool(_) => "bool",
+            Self::Int(_) => "int",
+            Self::Number(_) => "number",
+            Self::String(_) => "string",
+            Self::Bytes(_) => "bytes",
+            Self::List(_) => "list",
+            Self::Object(_) => "object",
+            Self::Function(_) => "function",
+        }
+    }
+
     pub fn as_bool(&self) -> Option<bool> {
         match self {
             Self::Bool(b) => Some(*b),
@@ -79,8 +154,17 @@ impl Value {
         }
     }
 
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Any numeric value, integers being converted to floats.
     pub fn as_number(&self) -> Option<f64> {
         match self {
+            Self::Int(n) => Some(*n as f64),
             Self::Number(n) => Some(*n),
             _ => None,
         }
@@ -93,9 +177,23 @@ impl Value {
         }
     }
 
-    pub fn as_object(&self) -> Option<&HashMap<String, Value>> {
+    pub fn as_bytes(&self) -> Option<&[u8]> {
         match self {
-            Self::Object(h) => Some(h),
+            Self::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Self::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<Ref<'_, HashMap<String, Value>>> {
+        match self {
+            Self::Object(object) => Some(object.borrow()),
             _ => None,
         }
     }
@@ -106,6 +204,102 @@ impl Value {
             _ => None,
         }
     }
+
+    /// The value as porte source would write it, strings quoted and object fields sorted.
+    ///
+    /// Functions are shown as `<function/arity>` and objects containing themselves as `{...}`
+    /// where they recur.
+    pub fn repr(&self) -> String {
+        let mut output = String::new();
+        write_repr(self, &mut Vec::new(), &mut output);
+        output
+    }
+}
+
+fn write_repr(value: &Value, enclosing: &mut Vec<ObjectRef>, output: &mut String) {
+    match value {
+        Value::None => output.push_str("none"),
+        Value::Bool(bool) => output.push_str(&bool.to_string()),
+        Value::Int(n) => output.push_str(&n.to_string()),
+        Value::Number(n) => output.push_str(&format!("{n:?}")),
+        Value::String(string) => {
+            output.push('"');
+            let mut characters = string.chars().peekable();
+            while let Some(c) = characters.next() {
+                match c {
+                    '"' => output.push_str("\\\""),
+                    '\\' => output.push_str("\\\\"),
+                    '\n' => output.push_str("\\n"),
+                    '\t' => output.push_str("\\t"),
+                    '$' if characters.peek() == Some(&'{') => output.push_str("\\$"),
+                    c if c.is_control() => output.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                    c => output.push(c),
+                }
+            }
+            output.push('"');
+        }
+        Value::Bytes(bytes) => {
+            let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+            output.push_str(&format!("from_hex(\"{hex}\")"));
+        }
+        Value::List(items) => {
+            output.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push_str(", ");
+                }
+                write_repr(item, enclosing, output);
+            }
+            output.push(']');
+        }
+        Value::Object(object) if enclosing.iter().any(|outer| Rc::ptr_eq(outer, object)) => {
+            output.push_str("{...}")
+        }
+        Value::Object(object) if object.borrow().is_empty() => output.push_str("obj()"),
+        Value::Object(object) => {
+            enclosing.push(object.clone());
+            let fields = object.borrow();
+            let mut names: Vec<_> = fields.keys().collect();
+            names.sort();
+            output.push_str("{ ");
+            for (index, name) in names.into_iter().enumerate() {
+                if index > 0 {
+                    output.push_str(", ");
+                }
+                output.push_str(name);
+                output.push_str(": ");
+                write_repr(&fields[name], enclosing, output);
+            }
+            output.push_str(" }");
+            enclosing.pop();
+        }
+        Value::Function(function) => {
+            output.push_str(&format!("<function/{}>", function.argument_count))
+        }
+    }
+}
+
+#[test]
+fn test_value_repr() {
+    let object: Value = HashMap::from([
+        ("b".to_string(), Value::from("say \"${hi}\"\n")),
+        (
+            "a".to_string(),
+            Value::List(vec![1.into(), 2.5.into(), Value::None]),
+        ),
+    ])
+    .into();
+    assert_eq!(
+        object.repr(),
+        r#"{ a: [1, 2.5, none], b: "say \"\${hi}\"\n" }"#
+    );
+    let Value::Object(fields) = &object else {
+        unreachable!()
+    };
+    fields.borrow_mut().insert("c".into(), object.clone());
+    assert!(object.repr().ends_with(", c: {...} }"));
+    assert_eq!(Value::from(vec![0u8, 255]).repr(), r#"from_hex("00ff")"#);
+    assert_eq!(Value::from(HashMap::new()).repr(), "obj()");
 }
 
 impl From<bool> for Value {
@@ -114,6 +308,12 @@ impl From<bool> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
 impl From<f64> for Value {
     fn from(value: f64) -> Self {
         Self::Number(value)
@@ -132,9 +332,21 @@ impl From<&str> for Value {
     }
 }
 
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Bytes(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::List(value)
+    }
+}
+
 impl From<HashMap<String, Value>> for Value {
     fn from(value: HashMap<String, Value>) -> Self {
-        Self::Object(value)
+        Self::Object(Rc::new(RefCell::new(value)))
     }
 }
 